@@ -0,0 +1,122 @@
+use crate::forge::{Forge, Repo};
+use crate::github::AssetLevel;
+use crate::{Ecosystem, default_branch, get_dependabot_yml_content};
+use indexmap::IndexMap;
+
+/// One row of the audit table.
+struct AuditRow {
+    repo: String,
+    ecosystems: Vec<String>,
+    has_config: bool,
+    level: AssetLevel,
+}
+
+/// Prints which of the selected repos with detected ecosystems have a dependabot config, without
+/// writing anything. Repos without a selected repository-level are left out, like when generating.
+pub async fn print_audit(
+    forge: &impl Forge,
+    repos: &[&Repo],
+    ecosystems: &IndexMap<String, Vec<(String, Ecosystem)>>,
+    levels: &[AssetLevel],
+    level_property: &str,
+    default_level: Option<AssetLevel>,
+    dependabot_filename: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut rows = vec![];
+    for repo in repos {
+        let Some(manifests) = ecosystems
+            .get(&repo.full_name)
+            .filter(|manifests| !manifests.is_empty())
+        else {
+            continue;
+        };
+
+        let props = forge.custom_properties(&repo.name).await?;
+        let Some(level) = AssetLevel::get_from_props(&props, level_property).or(default_level)
+        else {
+            continue;
+        };
+        let selected = if levels.is_empty() {
+            level != AssetLevel::Playground
+        } else {
+            levels.contains(&level)
+        };
+        if !selected {
+            continue;
+        }
+
+        let mut names = manifests
+            .iter()
+            .map(|(_, ecosystem)| ecosystem.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        rows.push(AuditRow {
+            repo: repo.name.clone(),
+            ecosystems: names,
+            has_config: get_dependabot_yml_content(
+                forge,
+                repo,
+                default_branch(repo),
+                dependabot_filename,
+            )
+            .await?
+            .is_some(),
+            level,
+        });
+    }
+
+    // Coverage gaps first, so they are easy to spot.
+    rows.sort_by(|a, b| {
+        a.has_config
+            .cmp(&b.has_config)
+            .then_with(|| a.repo.cmp(&b.repo))
+    });
+
+    let table = rows
+        .iter()
+        .map(|row| {
+            [
+                row.repo.clone(),
+                row.ecosystems.join(", "),
+                if row.has_config { "yes" } else { "no" }.to_string(),
+                row.level.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(["repo", "ecosystems", "has-config", "asset-level"], &table);
+
+    let missing = rows.iter().filter(|row| !row.has_config).count();
+    println!(
+        "{missing} of {} repos have no dependabot config",
+        rows.len()
+    );
+
+    Ok(())
+}
+
+/// Prints the rows with every column padded to its widest cell.
+fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let print_row = |cells: [&str; N]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+
+    print_row(header);
+    for row in rows {
+        print_row(row.each_ref().map(String::as_str));
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::Context;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes the temporary files of concurrent writers within this process.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`. Readers,
+/// including concurrent runs sharing a cache, see either the old or the new file but never a
+/// truncated one.
+pub fn write_atomically(path: impl AsRef<Path>, contents: &str) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        path.file_name()
+            .context("path must name a file")?
+            .to_string_lossy(),
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    fs::write(&temp_path, contents)
+        .with_context(|| format!("failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| {
+        // Don't leave the temporary file behind, the rename error is what matters.
+        let _ = fs::remove_file(&temp_path);
+        format!("failed to replace {}", path.display())
+    })
+}
@@ -0,0 +1,120 @@
+use crate::forge::{Forge, Repo};
+use crate::{Ecosystem, add_manifest, fetch_manifest, manifest_directory, manifest_path};
+use anyhow::Context;
+use futures::{StreamExt, stream};
+use indexmap::IndexMap;
+
+/// Replaces the roots of virtual Cargo workspaces, i.e. a `[workspace]` without a `[package]`, by
+/// their members. Dependabot finds no crate to update in a virtual manifest. Glob members like
+/// `crates/*` are kept as globs.
+///
+/// Only the manifests of `repos` are read, `concurrency` repos at a time.
+pub async fn expand_virtual_workspaces(
+    forge: &impl Forge,
+    repos: &[&Repo],
+    ecosystems: IndexMap<String, Vec<(String, Ecosystem)>>,
+    concurrency: usize,
+) -> anyhow::Result<IndexMap<String, Vec<(String, Ecosystem)>>> {
+    // Buffered instead of unordered, so that the order of the repos stays stable.
+    Ok(stream::iter(ecosystems)
+        .map(|(full_name, entries)| async move {
+            let repo = repos.iter().find(|repo| repo.full_name == full_name);
+            let entries = match repo {
+                Some(repo) => expand_repo(forge, repo, entries).await,
+                None => entries,
+            };
+            (full_name, entries)
+        })
+        .buffered(concurrency)
+        .collect()
+        .await)
+}
+
+/// Expands the virtual workspaces among the manifests of one repo.
+async fn expand_repo(
+    forge: &impl Forge,
+    repo: &Repo,
+    entries: Vec<(String, Ecosystem)>,
+) -> Vec<(String, Ecosystem)> {
+    let mut new_entries = vec![];
+    for (path, ecosystem) in entries {
+        let members = match ecosystem {
+            // Keep the root if the manifest cannot be read, as before.
+            Ecosystem::Cargo => match virtual_workspace_members(forge, repo, &path).await {
+                Ok(members) => members,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to read {} of {}: {:#}",
+                        manifest_path(&path),
+                        repo.name,
+                        e
+                    );
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        let Some(members) = members else {
+            add_manifest(&mut new_entries, (path, ecosystem));
+            continue;
+        };
+
+        log::debug!(
+            "{} of {} is a virtual workspace with the members {:?}",
+            manifest_path(&path),
+            repo.full_name,
+            members
+        );
+        // Same format as the paths of code search results, see `manifest_directory`.
+        let prefix = path.strip_suffix(&manifest_path(&path)).unwrap_or(&path);
+        let root = manifest_directory(&path);
+        for member in members {
+            let member_path = [root.trim_matches('/'), member.trim_matches('/')]
+                .into_iter()
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<_>>()
+                .join("/");
+            add_manifest(
+                &mut new_entries,
+                (
+                    format!("{prefix}{member_path}/Cargo.toml"),
+                    Ecosystem::Cargo,
+                ),
+            );
+        }
+    }
+
+    new_entries
+}
+
+/// The members of the workspace if the Cargo.toml at `path` is a virtual manifest.
+async fn virtual_workspace_members(
+    forge: &impl Forge,
+    repo: &Repo,
+    path: &str,
+) -> anyhow::Result<Option<Vec<String>>> {
+    let Some(content) = fetch_manifest(forge, repo, path).await? else {
+        return Ok(None);
+    };
+
+    let manifest = toml::from_str::<toml::Table>(&content).context("failed to parse manifest")?;
+    if manifest.contains_key("package") {
+        return Ok(None);
+    }
+
+    let members = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array())
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|member| member.as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // Without members there is nothing better than the root.
+    Ok((!members.is_empty()).then_some(members))
+}
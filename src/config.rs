@@ -0,0 +1,140 @@
+use crate::{Args, DependabotOverrides};
+use anyhow::Context;
+use serde::Deserialize;
+use std::fs;
+
+/// Settings for scheduled runs, read from the file given by --config. Command line flags take
+/// precedence over the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RunConfig {
+    pub org: Option<String>,
+    /// Branch the generated config is committed to.
+    pub branch: Option<String>,
+    pub dependabot_overrides: Option<String>,
+    pub ecosystems_cache: Option<String>,
+    /// Users the created config PRs are assigned to.
+    pub assignees: Vec<String>,
+    /// Team slugs of the org that are asked to review the created config PRs.
+    pub team_reviewers: Vec<String>,
+    /// Same sections as the overrides file (`defaults`, `registries`, `updates` and
+    /// `target-branches`), used unless an overrides file is given.
+    #[serde(flatten)]
+    pub overrides: DependabotOverrides,
+}
+
+impl RunConfig {
+    pub fn load(path: &str) -> anyhow::Result<RunConfig> {
+        let contents = fs::read_to_string(path).context("failed to read config file")?;
+        toml::from_str(&contents).context("failed to parse config TOML")
+    }
+
+    /// Fills everything not passed on the command line from the file.
+    pub fn apply_to(&self, args: &mut Args) -> anyhow::Result<()> {
+        if args.org.is_empty() {
+            args.org = self
+                .org
+                .clone()
+                .context("organization name must be passed or set in the config file")?;
+        }
+        if args.branch.is_none() {
+            args.branch = self.branch.clone();
+        }
+        if args.dependabot_overrides.is_none() {
+            args.dependabot_overrides = self.dependabot_overrides.clone();
+        }
+        if args.ecosystems_cache.is_none() {
+            args.ecosystems_cache = self.ecosystems_cache.clone();
+        }
+        if args.assignee.is_empty() {
+            args.assignee = self.assignees.clone();
+        }
+        if args.team_reviewer.is_empty() {
+            args.team_reviewer = self.team_reviewers.clone();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a file named `name` in a fresh temporary directory.
+    fn write_temp(name: &str, contents: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "dependabot-org-config-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn assert_overrides(overrides: &DependabotOverrides) {
+        assert_eq!(
+            overrides.defaults.group_exclude_patterns,
+            ["ezpz", "kcl*", "kittycad*"]
+        );
+        let cli = &overrides.updates["cli"];
+        assert_eq!(cli.len(), 1);
+        assert_eq!(cli[0].package_ecosystem.as_deref(), Some("cargo"));
+        assert_eq!(
+            cli[0].ignore.as_ref().unwrap()[0]
+                .dependency_name
+                .as_deref(),
+            Some("kittycad-modeling-cmds")
+        );
+        assert_eq!(overrides.target_branches["cli"], ["release/1.x"]);
+    }
+
+    #[test]
+    fn loads_toml_overrides() {
+        let path = write_temp(
+            "overrides.toml",
+            r#"
+            [defaults]
+            group-exclude-patterns = ["ezpz", "kcl*", "kittycad*"]
+
+            [[updates.cli]]
+            package-ecosystem = "cargo"
+            [[updates.cli.ignore]]
+            dependency-name = "kittycad-modeling-cmds"
+
+            [target-branches]
+            cli = ["release/1.x"]
+            "#,
+        );
+
+        assert_overrides(&DependabotOverrides::load(&path).unwrap());
+    }
+
+    #[test]
+    fn loads_yaml_overrides() {
+        let contents = "defaults:\n  \
+                        group-exclude-patterns: [ezpz, kcl*, kittycad*]\n\
+                        updates:\n  \
+                        cli:\n  \
+                        - package-ecosystem: cargo\n    \
+                        ignore:\n    \
+                        - dependency-name: kittycad-modeling-cmds\n\
+                        target-branches:\n  \
+                        cli: [release/1.x]\n";
+
+        for name in ["overrides.yaml", "overrides.yml"] {
+            let path = write_temp(name, contents);
+            assert_overrides(&DependabotOverrides::load(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_yaml_in_toml_file() {
+        let path = write_temp("yaml-overrides.toml", "defaults:\n  labels: [deps]\n");
+
+        let error = DependabotOverrides::load(&path).unwrap_err();
+        assert_eq!(error.to_string(), "failed to read overrides TOML from file");
+    }
+}
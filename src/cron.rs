@@ -0,0 +1,81 @@
+use croner::parser::{CronParser, Seconds, Year};
+
+/// Validates a five field cron expression like `0 9 1 * *`, as GitHub silently ignores schedules
+/// it cannot parse.
+///
+/// Supports `*`, values, names (e.g. `mon` or `jan`), ranges, lists and steps.
+pub fn validate_cron(expression: &str) -> anyhow::Result<()> {
+    let fields = expression.split_whitespace().count();
+    anyhow::ensure!(
+        fields == 5,
+        "cron expression {expression:?} must have 5 fields, got {fields}"
+    );
+
+    CronParser::builder()
+        .seconds(Seconds::Disallowed)
+        .year(Year::Disallowed)
+        .build()
+        .parse(expression)
+        .map_err(|e| anyhow::anyhow!("invalid cron expression {expression:?}: {e}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ranges_steps_lists_and_names() {
+        for expression in [
+            "0 9 * * *",
+            "0 9 * * 1-5",
+            "*/15 * * * *",
+            "0-30/10 */2 1-15/7 * *",
+            "0,30 8,12,18 * * mon,wed,fri",
+            "0 9 1 jan-jun SUN",
+            "0 0 * * 7",
+        ] {
+            assert!(
+                validate_cron(expression).is_ok(),
+                "{expression:?} should be valid"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for (expression, error) in [
+            (
+                "0 9 * *",
+                "cron expression \"0 9 * *\" must have 5 fields, got 4",
+            ),
+            (
+                "60 9 * * *",
+                "invalid cron expression \"60 9 * * *\": Component error: Number out of bounds.",
+            ),
+            (
+                "0 9 0 * *",
+                "invalid cron expression \"0 9 0 * *\": Component error: Number out of bounds.",
+            ),
+            (
+                "*/0 * * * *",
+                "invalid cron expression \"*/0 * * * *\": Component error: Step cannot be zero.",
+            ),
+            (
+                "*/x * * * *",
+                "invalid cron expression \"*/x * * * *\": Pattern contains illegal characters: CronPattern contains illegal character 'X' in part '*/X'",
+            ),
+            (
+                "0 9 * * 5-1",
+                "invalid cron expression \"0 9 * * 5-1\": Component error: Range out of bounds.",
+            ),
+            (
+                "0 9 * foo *",
+                "invalid cron expression \"0 9 * foo *\": Pattern contains illegal characters: CronPattern contains illegal character 'F' in part 'FOO'",
+            ),
+        ] {
+            assert_eq!(validate_cron(expression).unwrap_err().to_string(), error);
+        }
+    }
+}
@@ -1,11 +1,18 @@
+use crate::cron::validate_cron;
+use crate::glob::any_glob_match;
+use anyhow::Context;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct DependabotConfig {
     /// The configuration version (always 2)
     pub version: u32,
+    /// Lets Dependabot update ecosystems that are still in beta, e.g. "pub".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_beta_ecosystems: Option<bool>,
     /// Optional top-level private registries.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub registries: Option<IndexMap<String, Registry>>,
@@ -13,209 +20,562 @@ pub struct DependabotConfig {
     pub updates: Vec<Update>,
 }
 
-/// Same as Update just wiht optional Schedule
-#[derive(Debug, Serialize, Deserialize, Default)]
-#[serde(rename_all = "kebab-case")]
-pub struct UpdateOverride {
-    /// Defines the package ecosystem (e.g. "npm", "docker", etc.)
-    pub package_ecosystem: String,
-    /// A single directory path where the dependency manifests reside.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub directory: Option<String>,
-    /// Alternatively, a list of directories.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub directories: Option<Vec<String>>,
-    /// How often to check for updates.
-    pub schedule: Option<Schedule>,
-    /// Optional rules to allow specific dependencies.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub allow: Option<Vec<DependencyRule>>,
-    /// Optional rules to ignore certain dependencies or versions.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignore: Option<Vec<DependencyRule>>,
-    /// Optional assignees for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub assignees: Option<Vec<String>>,
-    /// Optional commit message configuration.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub commit_message: Option<CommitMessage>,
-    /// Optional labels for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub labels: Option<Vec<String>>,
-    /// Optionally associate a milestone (by numeric ID).
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub milestone: Option<u32>,
-    /// Limit on the maximum number of open pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub open_pull_requests_limit: Option<u32>,
-    /// Optionally override registries to use for this update.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub registries: Option<Vec<String>>,
-    /// Optional reviewers for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reviewers: Option<Vec<String>>,
-    /// Target branch for version updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub target_branch: Option<String>,
-    /// Whether vendored dependencies should be maintained.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub vendor: Option<bool>,
-    /// Strategy for updating version constraints.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub versioning_strategy: Option<String>,
-    /// Allow execution of external code during updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub insecure_external_code_execution: Option<bool>,
-    /// Optional configuration for the generated pull request branch names.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub pull_request_branch_name: Option<PullRequestBranchName>,
-    /// Optionally disable automatic rebasing.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub rebase_strategy: Option<String>,
-    /// Optional grouping rules.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub groups: Option<IndexMap<String, Group>>,
-    /// Optional cooldown configuration for dependency updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cooldown: Option<Cooldown>,
+impl DependabotConfig {
+    /// Serializes the config and annotates each update block with the manifests it was generated
+    /// from, e.g. `# detected from Cargo.toml`. Update blocks are separated by a blank line for
+    /// readability.
+    pub fn to_annotated_yaml(&self) -> anyhow::Result<String> {
+        let yaml = serde_yaml_ng::to_string(self)?;
+
+        let mut updates = self.updates.iter();
+        let mut in_updates = false;
+        let mut annotated = String::with_capacity(yaml.len());
+
+        for line in yaml.lines() {
+            if line == "updates:" {
+                in_updates = true;
+            } else if in_updates && line.starts_with("- ") {
+                // Every item of the updates sequence starts a new update block.
+                annotated.push('\n');
+                if let Some(update) = updates.next()
+                    && !update.detected_from.is_empty()
+                {
+                    annotated.push_str("# detected from ");
+                    annotated.push_str(&update.detected_from.join(", "));
+                    annotated.push('\n');
+                }
+            } else if !line.starts_with(' ') && !line.starts_with('-') {
+                in_updates = false;
+            }
+
+            annotated.push_str(line);
+            annotated.push('\n');
+        }
+
+        Ok(annotated)
+    }
+
+    /// Runs every validation, failing with the first problem.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self.validation_errors().into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs every validation and returns all problems, e.g. to lint a hand-written config.
+    pub fn validation_errors(&self) -> Vec<anyhow::Error> {
+        [
+            self.validate_registry_references(),
+            self.validate_groups(),
+            self.validate_schedules(),
+            self.validate_versioning_strategies(),
+            self.validate_allow_rules(),
+            self.validate_insecure_external_code_execution(),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+    }
+
+    /// Ensures that every registry referenced by an update is defined in the top-level registries.
+    pub fn validate_registry_references(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            for name in update.registries.iter().flatten() {
+                let defined = self
+                    .registries
+                    .as_ref()
+                    .is_some_and(|registries| registries.contains_key(name));
+
+                anyhow::ensure!(
+                    defined,
+                    "update for {} in {} references undefined registry {}",
+                    update.package_ecosystem,
+                    update.location(),
+                    name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the rendered YAML deserializes into the same config again. Fields that are
+    /// serialized differently than they are read (e.g. renamed or skipped ones) fail this check.
+    pub fn check_round_trip(&self) -> anyhow::Result<()> {
+        let yaml = self.to_annotated_yaml()?;
+        let parsed: DependabotConfig =
+            serde_yaml_ng::from_str(&yaml).context("rendered config cannot be parsed")?;
+
+        // The manifests are only rendered as comments.
+        let mut expected = self.clone();
+        for update in &mut expected.updates {
+            update.detected_from.clear();
+        }
+
+        if parsed == expected {
+            return Ok(());
+        }
+
+        // Point at the first differing update, printing the whole config is unreadable.
+        anyhow::ensure!(
+            parsed.updates.len() == expected.updates.len(),
+            "{} updates were rendered, but {} parsed",
+            expected.updates.len(),
+            parsed.updates.len()
+        );
+        for (expected, parsed) in expected.updates.iter().zip(&parsed.updates) {
+            anyhow::ensure!(
+                expected == parsed,
+                "update for {} in {} differs after a round trip, expected {:?}, got {:?}",
+                expected.package_ecosystem,
+                expected.location(),
+                expected,
+                parsed
+            );
+        }
+
+        anyhow::bail!(
+            "top-level fields differ after a round trip, expected {:?}, got {:?}",
+            DependabotConfig {
+                updates: vec![],
+                ..expected
+            },
+            DependabotConfig {
+                updates: vec![],
+                ..parsed
+            }
+        )
+    }
 
-    // Tool specific attributes:
-    /// Whether to disable grouping of updates.
-    #[serde(skip_serializing)]
-    pub groups_override: Option<bool>,
+    /// Normalizes the cooldown of every update, see [`Cooldown::normalize`].
+    pub fn normalize_cooldowns(&mut self) -> anyhow::Result<()> {
+        for update in &mut self.updates {
+            let location = update.location();
+            if let Some(cooldown) = &mut update.cooldown {
+                cooldown.normalize().with_context(|| {
+                    format!(
+                        "invalid cooldown for {} in {}",
+                        update.package_ecosystem, location
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the schedule of every update is valid, see [`Schedule::validate`].
+    pub fn validate_schedules(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            update.schedule.validate().with_context(|| {
+                format!(
+                    "invalid schedule for {} in {}",
+                    update.package_ecosystem,
+                    update.location()
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Every pair of package ecosystem and directory the config has an update for, regardless of
+    /// how the directories are split into update blocks.
+    pub fn ecosystem_directories(&self) -> BTreeSet<(&str, &str)> {
+        self.updates
+            .iter()
+            .flat_map(|update| {
+                update
+                    .directory
+                    .iter()
+                    .chain(update.directories.iter().flatten())
+                    .map(|directory| (update.package_ecosystem.as_str(), directory.as_str()))
+            })
+            .collect()
+    }
+
+    /// Ensures that every versioning strategy is supported by the ecosystem of its update.
+    pub fn validate_versioning_strategies(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            let Some(strategy) = &update.versioning_strategy else {
+                continue;
+            };
+
+            let supported = versioning_strategies(&update.package_ecosystem);
+            anyhow::ensure!(
+                supported.contains(&strategy.as_str()),
+                "versioning strategy {} of the update for {} in {} is not supported, expected one of [{}]",
+                strategy,
+                update.package_ecosystem,
+                update.location(),
+                supported.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that external code execution is only configured for ecosystems that support it.
+    pub fn validate_insecure_external_code_execution(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            let Some(value) = &update.insecure_external_code_execution else {
+                continue;
+            };
+
+            anyhow::ensure!(
+                value == "allow" || value == "deny",
+                "insecure-external-code-execution {} of the update for {} in {} is not supported, expected allow or deny",
+                value,
+                update.package_ecosystem,
+                update.location()
+            );
+            anyhow::ensure!(
+                EXTERNAL_CODE_EXECUTION_ECOSYSTEMS.contains(&update.package_ecosystem.as_str()),
+                "insecure-external-code-execution is not supported for {} in {}, only for [{}]",
+                update.package_ecosystem,
+                update.location(),
+                EXTERNAL_CODE_EXECUTION_ECOSYSTEMS.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the dependency types of all allow rules are supported by the ecosystem of
+    /// their update.
+    pub fn validate_allow_rules(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            for rule in update.allow.iter().flatten() {
+                let Some(dependency_type) = &rule.dependency_type else {
+                    continue;
+                };
+
+                let supported = allow_dependency_types(&update.package_ecosystem);
+                anyhow::ensure!(
+                    supported.contains(&dependency_type.as_str()),
+                    "dependency type {} of an allow rule of the update for {} in {} is not supported, expected one of [{}]",
+                    dependency_type,
+                    update.package_ecosystem,
+                    update.location(),
+                    supported.join(", ")
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that every group has a name and at least one rule, as Dependabot rejects others.
+    pub fn validate_groups(&self) -> anyhow::Result<()> {
+        for update in &self.updates {
+            for (name, group) in update.groups.iter().flatten() {
+                anyhow::ensure!(
+                    !name.trim().is_empty(),
+                    "update for {} in {} has a group with an empty name",
+                    update.package_ecosystem,
+                    update.location()
+                );
+
+                anyhow::ensure!(
+                    group.patterns.is_some()
+                        || group.applies_to.is_some()
+                        || group.dependency_type.is_some()
+                        || group.update_types.is_some(),
+                    "group {} of the update for {} in {} needs at least one of patterns, applies-to, dependency-type or update-types",
+                    name,
+                    update.package_ecosystem,
+                    update.location()
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[serde(rename_all = "kebab-case")]
-pub struct Update {
-    /// Defines the package ecosystem (e.g. "npm", "docker", etc.)
-    pub package_ecosystem: String,
-    /// A single directory path where the dependency manifests reside.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub directory: Option<String>,
-    /// Alternatively, a list of directories.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub directories: Option<Vec<String>>,
-    /// How often to check for updates.
-    pub schedule: Schedule,
-    /// Optional rules to allow specific dependencies.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub allow: Option<Vec<DependencyRule>>,
-    /// Optional rules to ignore certain dependencies or versions.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignore: Option<Vec<DependencyRule>>,
-    /// Optional assignees for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub assignees: Option<Vec<String>>,
-    /// Optional commit message configuration.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub commit_message: Option<CommitMessage>,
-    /// Optional labels for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub labels: Option<Vec<String>>,
-    /// Optionally associate a milestone (by numeric ID).
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub milestone: Option<u32>,
-    /// Limit on the maximum number of open pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub open_pull_requests_limit: Option<u32>,
-    /// Optionally override registries to use for this update.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub registries: Option<Vec<String>>,
-    /// Optional reviewers for pull requests.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reviewers: Option<Vec<String>>,
-    /// Target branch for version updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub target_branch: Option<String>,
-    /// Whether vendored dependencies should be maintained.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub vendor: Option<bool>,
-    /// Strategy for updating version constraints.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub versioning_strategy: Option<String>,
-    /// Allow execution of external code during updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub insecure_external_code_execution: Option<bool>,
-    /// Optional configuration for the generated pull request branch names.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub pull_request_branch_name: Option<PullRequestBranchName>,
-    /// Optionally disable automatic rebasing.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub rebase_strategy: Option<String>,
-    /// Optional grouping rules.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub groups: Option<IndexMap<String, Group>>,
-    /// Optional cooldown configuration for dependency updates.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cooldown: Option<Cooldown>,
+/// Declares [`Update`] and [`UpdateOverride`] from a single list of Dependabot options, so that
+/// a new option cannot be forgotten in the override or in [`Update::override_config`].
+///
+/// The listed options are optional in both structs and an override simply replaces them. Options
+/// that merge differently (ecosystem, schedule, groups and cooldown) are spelled out below. The
+/// two lists only exist to keep the order of the serialized keys.
+macro_rules! update_options {
+    (
+        before_schedule { $($(#[doc = $before_doc:literal])* $before:ident: $before_ty:ty,)* }
+        after_schedule { $($(#[doc = $after_doc:literal])* $after:ident: $after_ty:ty,)* }
+    ) => {
+        /// Same as Update just with every option optional.
+        #[derive(Debug, Serialize, Deserialize, Default)]
+        #[serde(rename_all = "kebab-case")]
+        pub struct UpdateOverride {
+            /// Defines the package ecosystem (e.g. "npm", "docker", etc.)
+            ///
+            /// Without an ecosystem the override applies to all updates of the repo. Overrides for a
+            /// specific ecosystem take precedence.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub package_ecosystem: Option<String>,
+            $(
+                $(#[doc = $before_doc])*
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $before: Option<$before_ty>,
+            )*
+            /// How often to check for updates.
+            pub schedule: Option<Schedule>,
+            $(
+                $(#[doc = $after_doc])*
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $after: Option<$after_ty>,
+            )*
+            /// Optional grouping rules.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub groups: Option<IndexMap<String, Group>>,
+            /// Optional cooldown configuration for dependency updates.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub cooldown: Option<Cooldown>,
+
+            // Tool specific attributes:
+            /// Whether to disable grouping of updates.
+            #[serde(skip_serializing)]
+            pub groups_override: Option<bool>,
+            /// Whether the cooldown include/exclude lists are added to the generated ones instead of
+            /// replacing them.
+            #[serde(skip_serializing)]
+            pub cooldown_additive: Option<bool>,
+        }
+
+        #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+        #[serde(rename_all = "kebab-case")]
+        pub struct Update {
+            /// Defines the package ecosystem (e.g. "npm", "docker", etc.)
+            pub package_ecosystem: String,
+            $(
+                $(#[doc = $before_doc])*
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $before: Option<$before_ty>,
+            )*
+            /// How often to check for updates.
+            pub schedule: Schedule,
+            $(
+                $(#[doc = $after_doc])*
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $after: Option<$after_ty>,
+            )*
+            /// Optional grouping rules.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub groups: Option<IndexMap<String, Group>>,
+            /// Optional cooldown configuration for dependency updates.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub cooldown: Option<Cooldown>,
+
+            // Tool specific attributes:
+            /// Manifests this update was generated from, rendered as a comment above the block.
+            #[serde(skip)]
+            pub detected_from: Vec<String>,
+        }
+
+        impl Update {
+            pub fn override_config(self, other: &UpdateOverride) -> Update {
+                // Exhaustive destructuring fails to compile once a field is added outside of the
+                // macro without handling it here.
+                let Update {
+                    package_ecosystem,
+                    $($before,)*
+                    schedule,
+                    $($after,)*
+                    groups,
+                    cooldown,
+                    detected_from,
+                } = self;
+                let UpdateOverride {
+                    package_ecosystem: _,
+                    $($before: _,)*
+                    schedule: _,
+                    $($after: _,)*
+                    groups: _,
+                    cooldown: _,
+                    groups_override: _,
+                    cooldown_additive: _,
+                } = other;
+
+                Update {
+                    package_ecosystem,
+                    $($before: other.$before.clone().or($before),)*
+                    schedule: other.schedule.clone().unwrap_or(schedule),
+                    $($after: other.$after.clone().or($after),)*
+                    groups: merge_groups(groups, other),
+                    cooldown: merge_cooldown(cooldown, other),
+                    detected_from,
+                }
+            }
+        }
+    };
+}
+
+update_options! {
+    before_schedule {
+        /// A single directory path where the dependency manifests reside.
+        directory: String,
+        /// Alternatively, a list of directories.
+        directories: Vec<String>,
+    }
+    after_schedule {
+        /// Optional rules to allow specific dependencies.
+        allow: Vec<DependencyRule>,
+        /// Optional rules to ignore certain dependencies or versions.
+        ignore: Vec<DependencyRule>,
+        /// Optional assignees for pull requests.
+        assignees: Vec<String>,
+        /// Optional commit message configuration.
+        commit_message: CommitMessage,
+        /// Optional labels for pull requests.
+        labels: Vec<String>,
+        /// Optionally associate a milestone (by numeric ID).
+        milestone: u32,
+        /// Limit on the maximum number of open pull requests.
+        open_pull_requests_limit: u32,
+        /// Optionally override registries to use for this update.
+        registries: Vec<String>,
+        /// Optional reviewers for pull requests.
+        reviewers: Vec<String>,
+        /// Target branch for version updates.
+        target_branch: String,
+        /// Whether vendored dependencies should be maintained.
+        vendor: bool,
+        /// Strategy for updating version constraints.
+        versioning_strategy: String,
+        /// Allow ("allow") or deny ("deny") execution of external code during updates.
+        insecure_external_code_execution: String,
+        /// Optional configuration for the generated pull request branch names.
+        pull_request_branch_name: PullRequestBranchName,
+        /// Optionally disable automatic rebasing.
+        rebase_strategy: String,
+    }
 }
 
 impl Update {
-    pub fn override_config(self, other: &UpdateOverride) -> Update {
-        Update {
-            package_ecosystem: self.package_ecosystem,
-            directory: other.directory.clone().or(self.directory.clone()),
-            directories: other.directories.clone().or(self.directories.clone()),
-            schedule: other.schedule.clone().unwrap_or(self.schedule.clone()),
-            allow: other.allow.clone().or(self.allow.clone()),
-            ignore: other.ignore.clone().or(self.ignore.clone()),
-            assignees: other.assignees.clone().or(self.assignees.clone()),
-            commit_message: other.commit_message.clone().or(self.commit_message.clone()),
-            labels: other.labels.clone().or(self.labels.clone()),
-            milestone: other.milestone.or(self.milestone),
-            open_pull_requests_limit: other
-                .open_pull_requests_limit
-                .or(self.open_pull_requests_limit),
-            registries: other.registries.clone().or(self.registries.clone()),
-            reviewers: other.reviewers.clone().or(self.reviewers.clone()),
-            target_branch: other.target_branch.clone().or(self.target_branch.clone()),
-            vendor: other.vendor.or(self.vendor),
-            versioning_strategy: other
-                .versioning_strategy
-                .clone()
-                .or(self.versioning_strategy.clone()),
-            insecure_external_code_execution: other
-                .insecure_external_code_execution
-                .or(self.insecure_external_code_execution),
-            pull_request_branch_name: other
-                .pull_request_branch_name
-                .clone()
-                .or(self.pull_request_branch_name.clone()),
-            rebase_strategy: other
-                .rebase_strategy
-                .clone()
-                .or(self.rebase_strategy.clone()),
-            groups: if other.groups_override.unwrap_or(false) {
-                other.groups.clone()
-            } else {
-                if let Some(other_groups) = &other.groups {
-                    if let Some(groups) = &self.groups {
-                        let mut merged_groups = other_groups.clone();
-
-                        for (key, group) in groups {
-                            merged_groups.insert(key.clone(), group.clone());
-                        }
-
-                        Some(merged_groups)
-                    } else {
-                        other.groups.clone()
-                    }
-                } else {
-                    self.groups
-                }
-            },
-            cooldown: other.cooldown.clone().or(self.cooldown.clone()),
+    /// The directory or directories of the update, for error messages.
+    fn location(&self) -> String {
+        let location = self
+            .directory
+            .clone()
+            .or_else(|| self.directories.as_ref().map(|dirs| dirs.join(", ")))
+            .unwrap_or_default();
+        match &self.target_branch {
+            Some(branch) => format!("{location} on {branch}"),
+            None => location,
+        }
+    }
+}
+
+/// The versioning strategies Dependabot supports for `ecosystem`, see
+/// https://docs.github.com/en/code-security/dependabot/working-with-dependabot/dependabot-options-reference#versioning-strategy--.
+fn versioning_strategies(ecosystem: &str) -> &'static [&'static str] {
+    match ecosystem {
+        "composer" | "npm" | "pip" => &[
+            "auto",
+            "increase",
+            "increase-if-necessary",
+            "lockfile-only",
+            "widen",
+        ],
+        "bundler" | "uv" => &["auto", "increase", "increase-if-necessary", "lockfile-only"],
+        "cargo" => &["auto", "increase-if-necessary", "lockfile-only"],
+        "pub" => &["auto", "increase", "increase-if-necessary", "widen"],
+        "mix" => &["auto", "lockfile-only"],
+        _ => &[],
+    }
+}
+
+/// IANA time zone names, Dependabot silently falls back to UTC for unknown ones.
+const TIMEZONES: &str = include_str!("../schema/timezones.txt");
+
+fn validate_timezone(timezone: &str) -> anyhow::Result<()> {
+    let mut timezones = TIMEZONES.lines().filter(|line| !line.starts_with('#'));
+    if timezones.clone().any(|known| known == timezone) {
+        return Ok(());
+    }
+
+    match timezones.find(|known| known.eq_ignore_ascii_case(timezone.trim())) {
+        Some(known) => anyhow::bail!("unknown timezone {timezone}, did you mean {known}?"),
+        None => anyhow::bail!(
+            "unknown timezone {timezone}, expected an IANA name like UTC or America/Los_Angeles"
+        ),
+    }
+}
+
+/// Ensures that the time is in the "hh:mm" format Dependabot expects, e.g. "09:30".
+fn validate_time(time: &str) -> anyhow::Result<()> {
+    let valid = time.split_once(':').is_some_and(|(hours, minutes)| {
+        hours.len() == 2
+            && minutes.len() == 2
+            && (hours.chars().chain(minutes.chars())).all(|c| c.is_ascii_digit())
+            && hours.parse::<u8>().is_ok_and(|hours| hours < 24)
+            && minutes.parse::<u8>().is_ok_and(|minutes| minutes < 60)
+    });
+    anyhow::ensure!(valid, "invalid schedule time {time}, expected hh:mm");
+
+    Ok(())
+}
+
+/// The ecosystems whose package managers may execute external code while Dependabot updates them.
+const EXTERNAL_CODE_EXECUTION_ECOSYSTEMS: &[&str] = &["bundler", "mix", "pip"];
+
+/// The dependency types of allow rules Dependabot supports for `ecosystem`. Only some package
+/// managers distinguish production and development dependencies.
+fn allow_dependency_types(ecosystem: &str) -> &'static [&'static str] {
+    match ecosystem {
+        "bundler" | "composer" | "maven" | "mix" | "npm" | "pip" | "uv" => {
+            &["direct", "indirect", "all", "production", "development"]
+        }
+        _ => &["direct", "indirect", "all"],
+    }
+}
+
+/// Adds the groups of the override to the generated ones, unless `groups-override` replaces them.
+fn merge_groups(
+    groups: Option<IndexMap<String, Group>>,
+    other: &UpdateOverride,
+) -> Option<IndexMap<String, Group>> {
+    if other.groups_override.unwrap_or(false) {
+        return other.groups.clone();
+    }
+
+    match (&other.groups, groups) {
+        (Some(other_groups), Some(groups)) => {
+            let mut merged_groups = other_groups.clone();
+
+            for (key, group) in groups {
+                merged_groups.insert(key, group);
+            }
+
+            Some(merged_groups)
         }
+        (Some(other_groups), None) => Some(other_groups.clone()),
+        (None, groups) => groups,
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+/// Merges the cooldown per field, see [`Cooldown::override_config`] and
+/// [`Cooldown::extend_config`].
+fn merge_cooldown(cooldown: Option<Cooldown>, other: &UpdateOverride) -> Option<Cooldown> {
+    match (other.cooldown.as_ref(), cooldown) {
+        (Some(other_cooldown), Some(cooldown)) if other.cooldown_additive.unwrap_or(false) => {
+            Some(cooldown.extend_config(other_cooldown))
+        }
+        (Some(other_cooldown), Some(cooldown)) => Some(cooldown.override_config(other_cooldown)),
+        (Some(other_cooldown), None) => Some(other_cooldown.clone()),
+        (None, cooldown) => cooldown,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Schedule {
     /// The frequency for checking updates: "daily", "weekly", or "monthly".
     pub interval: String,
-    /// Optional day for weekly updates (e.g. "monday").
+    /// Optional day for weekly updates (e.g. "monday"). Monthly updates always run on the first of
+    /// the month, use a `cron` interval for any other day of the month.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub day: Option<String>,
     /// Optional time of day to run the update (format "hh:mm").
@@ -224,12 +584,75 @@ pub struct Schedule {
     /// Optional timezone for the scheduled time.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timezone: Option<String>,
-    /// Optional cronjob expression for custom scheduling.
+    /// Cron expression for custom scheduling, requires the "cron" interval. Only five field cron
+    /// expressions are supported, not the natural language ones Dependabot also accepts.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cronjob: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Schedule {
+    const INTERVALS: [&str; 7] = [
+        "daily",
+        "weekly",
+        "monthly",
+        "quarterly",
+        "semiannually",
+        "yearly",
+        "cron",
+    ];
+
+    /// Rejects combinations Dependabot ignores or rejects, e.g. a weekday for monthly updates.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            Self::INTERVALS.contains(&self.interval.as_str()),
+            "unknown schedule interval {}, expected one of {}",
+            self.interval,
+            Self::INTERVALS.join(", ")
+        );
+        anyhow::ensure!(
+            self.day.is_none() || self.interval == "weekly",
+            "schedule day {} is only supported for the weekly interval, not {}",
+            self.day.as_deref().unwrap_or_default(),
+            self.interval
+        );
+        anyhow::ensure!(
+            (self.interval == "cron") == self.cronjob.is_some(),
+            "a cronjob requires the cron interval and vice versa, got interval {} and cronjob {:?}",
+            self.interval,
+            self.cronjob
+        );
+        if let Some(cronjob) = &self.cronjob {
+            validate_cron(cronjob)?;
+        }
+        if let Some(time) = &self.time {
+            validate_time(time)?;
+        }
+        if let Some(timezone) = &self.timezone {
+            validate_timezone(timezone)?;
+        }
+
+        Ok(())
+    }
+
+    /// Describes when updates run, e.g. `weekly saturday 03:00 America/Los_Angeles`. Updates with
+    /// the same slot run at the same time.
+    pub fn slot(&self) -> String {
+        [
+            Some(&self.interval),
+            self.cronjob.as_ref(),
+            self.day.as_ref(),
+            self.time.as_ref(),
+            self.timezone.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct CommitMessage {
     /// Prefix for all commit messages.
@@ -243,14 +666,14 @@ pub struct CommitMessage {
     pub include: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct PullRequestBranchName {
     /// Separator character to use in branch names.
     pub separator: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct DependencyRule {
     /// The dependency name pattern (supports wildcards).
@@ -267,7 +690,7 @@ pub struct DependencyRule {
     pub update_types: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Registry {
     /// The registry type (e.g. "docker-registry", "npm-registry", etc.).
@@ -288,7 +711,7 @@ pub struct Registry {
     pub replaces_base: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Group {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -307,7 +730,7 @@ pub struct Group {
     pub update_types: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Cooldown {
     /// Default cooldown period for dependencies without specific rules (in days).
@@ -326,6 +749,207 @@ pub struct Cooldown {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include: Option<Vec<String>>,
     /// List of dependencies excluded from cooldown (supports wildcards, up to 150 items).
+    ///
+    /// Dependabot applies this list to all update types. There is no way to exclude a dependency
+    /// only from e.g. the major cooldown.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exclude: Option<Vec<String>>,
 }
+
+impl Cooldown {
+    /// Dependabot accepts cooldown periods between 1 and 90 days.
+    const DAYS_RANGE: std::ops::RangeInclusive<u32> = 1..=90;
+
+    /// Overrides each cooldown period separately, so that e.g. only the major cooldown can be
+    /// changed while keeping the default period and the include/exclude lists.
+    pub fn override_config(self, other: &Cooldown) -> Cooldown {
+        Cooldown {
+            default_days: other.default_days.or(self.default_days),
+            semver_major_days: other.semver_major_days.or(self.semver_major_days),
+            semver_minor_days: other.semver_minor_days.or(self.semver_minor_days),
+            semver_patch_days: other.semver_patch_days.or(self.semver_patch_days),
+            include: other.include.clone().or(self.include),
+            exclude: other.exclude.clone().or(self.exclude),
+        }
+    }
+
+    /// Dependabot accepts at most this many include or exclude patterns.
+    const MAX_PATTERNS: usize = 150;
+
+    /// Sorts and dedups the include/exclude lists, which can contain duplicates after merging
+    /// defaults and overrides, and ensures they stay within the limit.
+    pub fn normalize(&mut self) -> anyhow::Result<()> {
+        for (name, patterns) in [
+            ("include", &mut self.include),
+            ("exclude", &mut self.exclude),
+        ] {
+            let Some(patterns) = patterns else {
+                continue;
+            };
+
+            patterns.sort();
+            patterns.dedup();
+            anyhow::ensure!(
+                patterns.len() <= Self::MAX_PATTERNS,
+                "cooldown {name} has {} patterns, but at most {} are allowed",
+                patterns.len(),
+                Self::MAX_PATTERNS
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Cooldown::override_config`], but adds the include/exclude patterns of `other` to the
+    /// existing ones. Patterns already covered by an existing wildcard are skipped.
+    pub fn extend_config(self, other: &Cooldown) -> Cooldown {
+        Cooldown {
+            include: merge_patterns(self.include.clone(), other.include.as_deref()),
+            exclude: merge_patterns(self.exclude.clone(), other.exclude.as_deref()),
+            ..self.override_config(other)
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, days) in [
+            ("default-days", self.default_days),
+            ("semver-major-days", self.semver_major_days),
+            ("semver-minor-days", self.semver_minor_days),
+            ("semver-patch-days", self.semver_patch_days),
+        ] {
+            if let Some(days) = days {
+                anyhow::ensure!(
+                    Self::DAYS_RANGE.contains(&days),
+                    "cooldown {name} must be between {} and {} days, got {days}",
+                    Self::DAYS_RANGE.start(),
+                    Self::DAYS_RANGE.end()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Unions two lists of dependency name patterns, skipping additions that an existing pattern
+/// already matches.
+fn merge_patterns(
+    existing: Option<Vec<String>>,
+    additions: Option<&[String]>,
+) -> Option<Vec<String>> {
+    let Some(additions) = additions else {
+        return existing;
+    };
+
+    let mut merged = existing.unwrap_or_default();
+    for pattern in additions {
+        if !any_glob_match(&merged, pattern) {
+            merged.push(pattern.clone());
+        }
+    }
+
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semver_cooldowns_serialize() {
+        let cooldown = Cooldown {
+            default_days: Some(7),
+            semver_major_days: Some(14),
+            semver_minor_days: Some(7),
+            semver_patch_days: Some(3),
+            include: None,
+            exclude: Some(vec!["kittycad*".to_string()]),
+        };
+
+        assert_eq!(
+            serde_yaml_ng::to_string(&cooldown).unwrap(),
+            "default-days: 7\n\
+             semver-major-days: 14\n\
+             semver-minor-days: 7\n\
+             semver-patch-days: 3\n\
+             exclude:\n\
+             - kittycad*\n"
+        );
+    }
+
+    #[test]
+    fn semver_cooldowns_override_separately() {
+        let defaults = Cooldown {
+            default_days: Some(7),
+            exclude: Some(vec!["kittycad*".to_string()]),
+            ..Cooldown::default()
+        };
+        let override_cooldown: Cooldown =
+            toml::from_str("semver-major-days = 14\nsemver-patch-days = 3").unwrap();
+
+        assert_eq!(
+            defaults.override_config(&override_cooldown),
+            Cooldown {
+                default_days: Some(7),
+                semver_major_days: Some(14),
+                semver_minor_days: None,
+                semver_patch_days: Some(3),
+                include: None,
+                exclude: Some(vec!["kittycad*".to_string()]),
+            }
+        );
+    }
+
+    fn update(ecosystem: &str, directory: &str, detected_from: &[&str]) -> Update {
+        Update {
+            package_ecosystem: ecosystem.to_string(),
+            directory: Some(directory.to_string()),
+            schedule: Schedule {
+                interval: "weekly".to_string(),
+                ..Schedule::default()
+            },
+            detected_from: detected_from.iter().map(ToString::to_string).collect(),
+            ..Update::default()
+        }
+    }
+
+    #[test]
+    fn comments_precede_their_update_block() {
+        let config = DependabotConfig {
+            version: 2,
+            updates: vec![
+                update("cargo", "/", &["Cargo.toml"]),
+                update("github-actions", "/", &[]),
+                update(
+                    "npm",
+                    "/web",
+                    &["web/package.json", "web/package-lock.json"],
+                ),
+            ],
+            ..DependabotConfig::default()
+        };
+
+        assert_eq!(
+            config.to_annotated_yaml().unwrap(),
+            "version: 2\n\
+             updates:\n\
+             \n\
+             # detected from Cargo.toml\n\
+             - package-ecosystem: cargo\n  \
+             directory: /\n  \
+             schedule:\n    \
+             interval: weekly\n\
+             \n\
+             - package-ecosystem: github-actions\n  \
+             directory: /\n  \
+             schedule:\n    \
+             interval: weekly\n\
+             \n\
+             # detected from web/package.json, web/package-lock.json\n\
+             - package-ecosystem: npm\n  \
+             directory: /web\n  \
+             schedule:\n    \
+             interval: weekly\n"
+        );
+    }
+}
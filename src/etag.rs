@@ -0,0 +1,72 @@
+use crate::cache;
+use anyhow::Context;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Remembers content API responses together with their ETag, so that repeated runs can send
+/// conditional requests. GitHub does not count those against the rate limit when the content is
+/// unchanged.
+pub struct EtagCache {
+    path: Option<String>,
+    responses: Mutex<IndexMap<String, CachedResponse>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+impl EtagCache {
+    /// Loads the ETags stored next to the ecosystems cache, e.g. `ecosystems.etags.json` for
+    /// `ecosystems.json`. Without an ecosystems cache nothing is remembered.
+    pub fn open(ecosystems_cache: Option<&str>) -> anyhow::Result<EtagCache> {
+        let path = ecosystems_cache.map(|cache| {
+            Path::new(cache)
+                .with_extension("etags.json")
+                .to_string_lossy()
+                .into_owned()
+        });
+
+        let responses = match &path {
+            Some(path) if fs::exists(path)? => {
+                let content = fs::read_to_string(path).context("failed to read ETag cache")?;
+                serde_json::from_str(&content).context("failed to parse ETag cache")?
+            }
+            _ => IndexMap::new(),
+        };
+
+        Ok(EtagCache {
+            path,
+            responses: Mutex::new(responses),
+        })
+    }
+
+    pub fn get(&self, uri: &str) -> Option<CachedResponse> {
+        self.responses
+            .lock()
+            .expect("lock poisoned")
+            .get(uri)
+            .cloned()
+    }
+
+    pub fn insert(&self, uri: &str, etag: String, body: String) {
+        self.responses
+            .lock()
+            .expect("lock poisoned")
+            .insert(uri.to_string(), CachedResponse { etag, body });
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let responses = self.responses.lock().expect("lock poisoned");
+        cache::write_atomically(path, &serde_json::to_string(&*responses)?)
+            .context("failed to write ETag cache")
+    }
+}
@@ -0,0 +1,381 @@
+use crate::forge::{DirEntry, FileTree, Forge, OpenedPr, Property, PullRequest, RemoteFile, Repo};
+use crate::github::AssetLevel;
+use indexmap::IndexMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An in-memory [`Forge`] standing in for GitHub in tests. Every write is recorded in
+/// [`FakeForge::calls`], so that tests can assert which API calls a run would make.
+#[derive(Default)]
+pub struct FakeForge {
+    repos: Vec<Repo>,
+    properties: IndexMap<String, Vec<Property>>,
+    /// Contents by repo, branch and path.
+    files: Mutex<IndexMap<(String, String, String), String>>,
+    branches: Mutex<Vec<(String, String)>>,
+    prs: Mutex<Vec<FakePr>>,
+    calls: Mutex<Vec<String>>,
+    /// Files being created right now and the most at any time, see `max_concurrent_writes`.
+    writing: AtomicUsize,
+    max_writing: AtomicUsize,
+}
+
+struct FakePr {
+    repo: String,
+    branch: String,
+    open: bool,
+    pr: PullRequest,
+}
+
+impl FakeForge {
+    /// Adds a non-empty repo of the `KittyCAD` org with `main` as default branch and the
+    /// `repository-level` custom property set to `level`.
+    pub fn with_repo(mut self, name: &str, level: AssetLevel) -> FakeForge {
+        self.repos.push(Repo {
+            id: self.repos.len() as u64 + 1,
+            name: name.to_string(),
+            full_name: format!("KittyCAD/{name}"),
+            default_branch: Some("main".to_string()),
+            archived: false,
+            size: Some(1),
+            pushed_at: None,
+            has_pages: false,
+        });
+        self.properties.insert(
+            name.to_string(),
+            vec![Property {
+                name: "repository-level".to_string(),
+                values: vec![level.to_string()],
+            }],
+        );
+        self.branches
+            .get_mut()
+            .unwrap()
+            .push((name.to_string(), "main".to_string()));
+        self
+    }
+
+    /// Adds a file on the default branch of the repo.
+    pub fn with_file(self, repo: &str, path: &str, content: &str) -> FakeForge {
+        self.files.lock().unwrap().insert(
+            (repo.to_string(), "main".to_string(), path.to_string()),
+            content.to_string(),
+        );
+        self
+    }
+
+    pub fn repo(&self, name: &str) -> &Repo {
+        self.repos
+            .iter()
+            .find(|repo| repo.name == name)
+            .expect("repo must exist")
+    }
+
+    pub fn file(&self, repo: &str, branch: &str, path: &str) -> Option<String> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(&(repo.to_string(), branch.to_string(), path.to_string()))
+            .cloned()
+    }
+
+    /// The writes so far, e.g. `open_pr engine ciso/update-dependabot -> main`.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The most files that were being created at the same time.
+    pub fn max_concurrent_writes(&self) -> usize {
+        self.max_writing.load(Ordering::SeqCst)
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn paths(&self, repo: &str, branch: &str) -> Vec<String> {
+        self.files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(file_repo, file_branch, _)| file_repo == repo && file_branch == branch)
+            .map(|(_, _, path)| path.clone())
+            .collect()
+    }
+}
+
+impl Forge for FakeForge {
+    async fn list_repos(&self) -> anyhow::Result<Vec<Repo>> {
+        Ok(self.repos.clone())
+    }
+
+    async fn get_file(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Option<RemoteFile>> {
+        Ok(self.file(repo, branch, path).map(|content| RemoteFile {
+            path: path.to_string(),
+            sha: format!("{branch}:{path}"),
+            content: Some(content),
+        }))
+    }
+
+    async fn list_directory(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Vec<DirEntry>> {
+        let mut entries: Vec<DirEntry> = vec![];
+        for file in self.paths(repo, branch) {
+            let Some(rest) = file.strip_prefix(&format!("{path}/")) else {
+                continue;
+            };
+            let (name, is_dir) = match rest.split_once('/') {
+                Some((name, _)) => (name, true),
+                None => (rest, false),
+            };
+            if !entries.iter().any(|entry| entry.name == name) {
+                entries.push(DirEntry {
+                    name: name.to_string(),
+                    path: format!("{path}/{name}"),
+                    is_dir,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn list_files(&self, repo: &str, branch: &str) -> anyhow::Result<FileTree> {
+        Ok(FileTree {
+            paths: self.paths(repo, branch),
+            truncated: false,
+        })
+    }
+
+    async fn create_branch(&self, repo: &str, branch: &str, from: &str) -> anyhow::Result<bool> {
+        let key = (repo.to_string(), branch.to_string());
+        if self.branches.lock().unwrap().contains(&key) {
+            return Ok(false);
+        }
+
+        self.record(format!("create_branch {repo} {branch} from {from}"));
+        let mut files = self.files.lock().unwrap();
+        let copies = files
+            .iter()
+            .filter(|((file_repo, file_branch, _), _)| file_repo == repo && file_branch == from)
+            .map(|((_, _, path), content)| {
+                (
+                    (repo.to_string(), branch.to_string(), path.clone()),
+                    content.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        files.extend(copies);
+        self.branches.lock().unwrap().push(key);
+
+        Ok(true)
+    }
+
+    async fn delete_branch(&self, repo: &str, branch: &str) -> anyhow::Result<bool> {
+        let key = (repo.to_string(), branch.to_string());
+        let mut branches = self.branches.lock().unwrap();
+        let existed = branches.contains(&key);
+        branches.retain(|other| *other != key);
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|(file_repo, file_branch, _), _| !(file_repo == repo && file_branch == branch));
+        self.record(format!("delete_branch {repo} {branch}"));
+
+        Ok(existed)
+    }
+
+    async fn create_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        path: &str,
+        _message: &str,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        self.record(format!("create_file {repo} {branch} {path}"));
+        // Gives concurrently processed repos the chance to start their writes in the meantime.
+        let writing = self.writing.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_writing.fetch_max(writing, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        self.writing.fetch_sub(1, Ordering::SeqCst);
+
+        self.files.lock().unwrap().insert(
+            (repo.to_string(), branch.to_string(), path.to_string()),
+            content.to_string(),
+        );
+        Ok(())
+    }
+
+    async fn update_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        _message: &str,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        self.record(format!("update_file {repo} {branch} {}", file.path));
+        self.files.lock().unwrap().insert(
+            (repo.to_string(), branch.to_string(), file.path.clone()),
+            content.to_string(),
+        );
+        Ok(())
+    }
+
+    async fn delete_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        _message: &str,
+    ) -> anyhow::Result<()> {
+        self.record(format!("delete_file {repo} {branch} {}", file.path));
+        self.files.lock().unwrap().shift_remove(&(
+            repo.to_string(),
+            branch.to_string(),
+            file.path.clone(),
+        ));
+        Ok(())
+    }
+
+    async fn open_pr(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        _draft: bool,
+    ) -> anyhow::Result<OpenedPr> {
+        let mut prs = self.prs.lock().unwrap();
+        if prs
+            .iter()
+            .any(|pr| pr.open && pr.repo == repo && pr.branch == branch)
+        {
+            return Ok(OpenedPr::AlreadyExists);
+        }
+
+        self.record(format!("open_pr {repo} {branch} -> {base}"));
+        let pr = PullRequest {
+            number: prs.len() as u64 + 1,
+            base: base.to_string(),
+            title: Some(title.to_string()),
+            body: Some(body.to_string()),
+            url: None,
+        };
+        prs.push(FakePr {
+            repo: repo.to_string(),
+            branch: branch.to_string(),
+            open: true,
+            pr: pr.clone(),
+        });
+
+        Ok(OpenedPr::Created(pr))
+    }
+
+    async fn open_prs(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: Option<&str>,
+    ) -> anyhow::Result<Vec<PullRequest>> {
+        Ok(self
+            .prs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|pr| pr.open && pr.repo == repo && pr.branch == branch)
+            .filter(|pr| base.is_none_or(|base| pr.pr.base == base))
+            .map(|pr| pr.pr.clone())
+            .collect())
+    }
+
+    async fn count_open_prs(&self, branch: &str) -> anyhow::Result<usize> {
+        Ok(self
+            .prs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|pr| pr.open && pr.branch == branch)
+            .count())
+    }
+
+    async fn update_pr(
+        &self,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        for pr in self.prs.lock().unwrap().iter_mut() {
+            if pr.repo == repo && pr.pr.number == number {
+                pr.pr.title = Some(title.to_string());
+                pr.pr.body = Some(body.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_pr_base(&self, repo: &str, number: u64, base: &str) -> anyhow::Result<()> {
+        self.record(format!("update_pr_base {repo} #{number} -> {base}"));
+        for pr in self.prs.lock().unwrap().iter_mut() {
+            if pr.repo == repo && pr.pr.number == number {
+                pr.pr.base = base.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    async fn close_pr(&self, repo: &str, number: u64) -> anyhow::Result<()> {
+        self.record(format!("close_pr {repo} #{number}"));
+        for pr in self.prs.lock().unwrap().iter_mut() {
+            if pr.repo == repo && pr.pr.number == number {
+                pr.open = false;
+            }
+        }
+        Ok(())
+    }
+
+    async fn can_assign(&self, _repo: &str, _user: &str) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn add_assignees(&self, repo: &str, number: u64, users: &[&str]) -> anyhow::Result<()> {
+        self.record(format!(
+            "add_assignees {repo} #{number} {}",
+            users.join(",")
+        ));
+        Ok(())
+    }
+
+    async fn team_exists(&self, _team: &str) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn request_team_reviews(
+        &self,
+        repo: &str,
+        number: u64,
+        teams: &[&str],
+    ) -> anyhow::Result<()> {
+        self.record(format!(
+            "request_team_reviews {repo} #{number} {}",
+            teams.join(",")
+        ));
+        Ok(())
+    }
+
+    async fn custom_properties(&self, repo: &str) -> anyhow::Result<Vec<Property>> {
+        Ok(self.properties.get(repo).cloned().unwrap_or_default())
+    }
+}
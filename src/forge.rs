@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+
+/// A repo of the org.
+#[derive(Debug, Clone)]
+pub struct Repo {
+    /// Identifies the repo in the paths of detected manifests, see `manifest_directory`.
+    pub id: u64,
+    pub name: String,
+    /// The name including the owner, e.g. `KittyCAD/modeling-app`.
+    pub full_name: String,
+    /// `None` for empty repos, which have no branches yet.
+    pub default_branch: Option<String>,
+    pub archived: bool,
+    /// The size in KB, `0` for empty repos.
+    pub size: Option<u32>,
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub has_pages: bool,
+}
+
+/// A custom property of a repo, e.g. its `repository-level`.
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: String,
+    /// Single-valued properties have one value, unset properties none.
+    pub values: Vec<String>,
+}
+
+/// The files of a branch of a repo.
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    /// The paths of all files, e.g. `services/api/go.mod`.
+    pub paths: Vec<String>,
+    /// Whether the forge returned only part of the files, as the repo is too big.
+    pub truncated: bool,
+}
+
+/// A file on a branch of a repo.
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+    pub path: String,
+    /// Identifies the current version of the file, needed to update or delete it.
+    pub sha: String,
+    /// The decoded content, if it is text.
+    pub content: Option<String>,
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    /// The branch the PR merges into.
+    pub base: String,
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub url: Option<String>,
+}
+
+/// The outcome of opening a PR.
+#[derive(Debug, Clone)]
+pub enum OpenedPr {
+    Created(PullRequest),
+    /// A PR for the branch is already open.
+    AlreadyExists,
+}
+
+/// The operations the config generation needs from a code hosting platform, so that it is not
+/// tied to GitHub. Repos are addressed by name within the org the forge was created for.
+///
+/// Ecosystem detection via code search is not part of this, as it relies on GitHub's code search.
+pub trait Forge {
+    /// All repos of the org.
+    async fn list_repos(&self) -> anyhow::Result<Vec<Repo>>;
+
+    /// The file at `path` on `branch`, `None` if it does not exist.
+    async fn get_file(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Option<RemoteFile>>;
+
+    /// The entries of the directory at `path` on `branch`, missing directories are empty.
+    async fn list_directory(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Vec<DirEntry>>;
+
+    /// The paths of all files on `branch`, see `--detection tree`.
+    async fn list_files(&self, repo: &str, branch: &str) -> anyhow::Result<FileTree>;
+
+    /// Creates `branch` from `from`, unless it already exists. Returns whether the branch was
+    /// created.
+    async fn create_branch(&self, repo: &str, branch: &str, from: &str) -> anyhow::Result<bool>;
+
+    /// Deletes `branch`. Returns whether it existed.
+    async fn delete_branch(&self, repo: &str, branch: &str) -> anyhow::Result<bool>;
+
+    async fn create_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        path: &str,
+        message: &str,
+        content: &str,
+    ) -> anyhow::Result<()>;
+
+    async fn update_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        message: &str,
+        content: &str,
+    ) -> anyhow::Result<()>;
+
+    async fn delete_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        message: &str,
+    ) -> anyhow::Result<()>;
+
+    /// Opens a PR merging `branch` into `base`, as draft if `draft` is set.
+    async fn open_pr(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> anyhow::Result<OpenedPr>;
+
+    /// The open PRs merging `branch` into `base`, or into any branch without a `base`.
+    async fn open_prs(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: Option<&str>,
+    ) -> anyhow::Result<Vec<PullRequest>>;
+
+    /// The number of open PRs from `branch` across all repos of the org.
+    async fn count_open_prs(&self, branch: &str) -> anyhow::Result<usize>;
+
+    async fn update_pr(
+        &self,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> anyhow::Result<()>;
+
+    /// Changes the branch the PR merges into.
+    async fn update_pr_base(&self, repo: &str, number: u64, base: &str) -> anyhow::Result<()>;
+
+    async fn close_pr(&self, repo: &str, number: u64) -> anyhow::Result<()>;
+
+    /// Whether `user` can be assigned to issues and PRs of the repo.
+    async fn can_assign(&self, repo: &str, user: &str) -> anyhow::Result<bool>;
+
+    async fn add_assignees(&self, repo: &str, number: u64, users: &[&str]) -> anyhow::Result<()>;
+
+    /// Whether the team with the slug `team` (e.g. "platform") belongs to the org.
+    async fn team_exists(&self, team: &str) -> anyhow::Result<bool>;
+
+    /// Requests reviews of the PR from the teams with the given slugs.
+    async fn request_team_reviews(
+        &self,
+        repo: &str,
+        number: u64,
+        teams: &[&str],
+    ) -> anyhow::Result<()>;
+
+    /// The custom properties of the repo, e.g. its `repository-level`.
+    async fn custom_properties(&self, repo: &str) -> anyhow::Result<Vec<Property>>;
+}
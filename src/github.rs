@@ -1,7 +1,17 @@
+use crate::etag::EtagCache;
+use crate::forge::{DirEntry, FileTree, Forge, OpenedPr, Property, PullRequest, RemoteFile, Repo};
+use anyhow::Context;
+use http::header::{ETAG, IF_NONE_MATCH};
+use http::{HeaderMap, HeaderValue, StatusCode};
 use octocrab::Octocrab;
-use octocrab::models::Repository;
+use octocrab::models::repos::{Content, Object};
+use octocrab::models::{AppId, Repository};
+use octocrab::params::State;
+use octocrab::params::repos::Reference;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum AssetLevel {
@@ -19,24 +29,47 @@ pub enum AssetLevel {
 }
 
 impl AssetLevel {
-    pub fn get_from_props(props: &[CustomProperty]) -> Option<AssetLevel> {
-        props
+    /// Reads the level from the custom property named `property`. Of multiple values the most
+    /// critical level wins, unknown values are ignored.
+    pub fn get_from_props(props: &[Property], property: &str) -> Option<AssetLevel> {
+        let prop = props.iter().find(|prop| prop.name == property)?;
+
+        prop.values
             .iter()
-            .find(|prop| prop.property_name == "repository-level")
-            .and_then(|prop| match &prop.value {
-                None => None,
-                Some(CustomPropertyValue::Array(_array)) => {
-                    panic!("Array not supported for repository-level")
+            .filter_map(|value| match value.parse::<AssetLevel>() {
+                Ok(level) => Some(level),
+                Err(e) => {
+                    log::debug!("Ignoring value of custom property {property}: {e}");
+                    None
                 }
-                Some(CustomPropertyValue::String(str)) => match str.as_str() {
-                    "Production" => Some(AssetLevel::Production),
-                    "Playground" => Some(AssetLevel::Playground),
-                    "Research & Development" => Some(AssetLevel::ResearchNDevelopment),
-                    "Corporate" => Some(AssetLevel::Corporate),
-                    "Non-essential Production" => Some(AssetLevel::NonEssentialProduction),
-                    _ => None,
-                },
             })
+            .max_by_key(|level| level.criticality())
+    }
+
+    /// Higher is more critical.
+    fn criticality(self) -> u8 {
+        match self {
+            AssetLevel::Playground => 0,
+            AssetLevel::ResearchNDevelopment => 1,
+            AssetLevel::Corporate => 2,
+            AssetLevel::NonEssentialProduction => 3,
+            AssetLevel::Production => 4,
+        }
+    }
+}
+
+impl FromStr for AssetLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Production" => Ok(AssetLevel::Production),
+            "Playground" => Ok(AssetLevel::Playground),
+            "Research & Development" => Ok(AssetLevel::ResearchNDevelopment),
+            "Corporate" => Ok(AssetLevel::Corporate),
+            "Non-essential Production" => Ok(AssetLevel::NonEssentialProduction),
+            _ => Err(format!("unknown asset level: {s}")),
+        }
     }
 }
 
@@ -58,6 +91,33 @@ pub struct CustomProperty {
     pub value: Option<CustomPropertyValue>,
 }
 
+impl From<CustomProperty> for Property {
+    fn from(prop: CustomProperty) -> Property {
+        Property {
+            name: prop.property_name,
+            values: match prop.value {
+                None => vec![],
+                Some(CustomPropertyValue::String(value)) => vec![value],
+                Some(CustomPropertyValue::Array(values)) => values,
+            },
+        }
+    }
+}
+
+/// Response of the git trees API, see https://docs.github.com/en/rest/git/trees.
+#[derive(Debug, Deserialize)]
+struct Tree {
+    tree: Vec<TreeEntry>,
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(untagged)]
 pub enum CustomPropertyValue {
@@ -87,6 +147,437 @@ impl CustomPropertyExt for Octocrab {
     }
 }
 
+/// The [`Forge`] of the repos of an org on GitHub.
+pub struct GitHub {
+    octocrab: Octocrab,
+    owner: String,
+    etags: EtagCache,
+}
+
+impl GitHub {
+    pub fn new(octocrab: Octocrab, owner: &str, etags: EtagCache) -> GitHub {
+        GitHub {
+            octocrab,
+            owner: owner.to_string(),
+            etags,
+        }
+    }
+
+    pub fn save_etags(&self) -> anyhow::Result<()> {
+        self.etags.save()
+    }
+
+    /// Fetches the file or directory at `path`, missing paths are empty. Sends the ETag of the
+    /// last response for the same path, so unchanged content is answered from the cache.
+    async fn get_content(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Vec<Content>> {
+        let uri = format!(
+            "/repos/{}/{}/contents/{}?ref={}",
+            self.owner,
+            repo,
+            encode_path(path),
+            utf8_percent_encode(branch, COMPONENT)
+        );
+        let cached = self.etags.get(&uri);
+
+        let mut headers = HeaderMap::new();
+        if let Some(cached) = &cached {
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(&cached.etag)?);
+        }
+
+        let response = self
+            .octocrab
+            ._get_with_headers(uri.as_str(), Some(headers))
+            .await
+            .with_context(|| format!("failed to fetch {path} of {repo}"))?;
+
+        let body = match (response.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => {
+                log::debug!("{path} of {repo} is unchanged");
+                cached.body
+            }
+            (status, _) if status.is_success() => {
+                let etag = response
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string);
+                let body = self.octocrab.body_to_string(response).await?;
+                if let Some(etag) = etag {
+                    self.etags.insert(&uri, etag, body.clone());
+                }
+                body
+            }
+            (StatusCode::NOT_FOUND, _) => return Ok(vec![]),
+            // Anything else, e.g. a rate limit, must not be mistaken for a missing file.
+            (status, _) => anyhow::bail!("failed to fetch {path} of {repo}: {status}"),
+        };
+
+        // Files are returned as a single object, directories as a list.
+        let json = serde_json::from_str::<serde_json::Value>(&body)
+            .with_context(|| format!("failed to parse content of {path} of {repo}"))?;
+        if json.is_array() {
+            Ok(serde_json::from_value(json)?)
+        } else {
+            Ok(vec![serde_json::from_value(json)?])
+        }
+    }
+}
+
+impl Forge for GitHub {
+    async fn list_repos(&self) -> anyhow::Result<Vec<Repo>> {
+        let repos = get_all_repos(&self.octocrab, &self.owner)
+            .await
+            .context("failed to fetch repos")?;
+
+        Ok(repos
+            .into_iter()
+            .map(|repo| Repo {
+                id: repo.id.0,
+                full_name: repo
+                    .full_name
+                    .unwrap_or_else(|| format!("{}/{}", self.owner, repo.name)),
+                name: repo.name,
+                default_branch: repo.default_branch,
+                archived: repo.archived.unwrap_or(false),
+                size: repo.size,
+                pushed_at: repo.pushed_at,
+                has_pages: repo.has_pages.unwrap_or(false),
+            })
+            .collect())
+    }
+
+    async fn get_file(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Option<RemoteFile>> {
+        let mut items = self.get_content(repo, path, branch).await?;
+
+        anyhow::ensure!(items.len() <= 1, "{path} in {repo} is a directory");
+
+        Ok(items.pop().map(|content| RemoteFile {
+            content: content.decoded_content(),
+            path: content.path,
+            sha: content.sha,
+        }))
+    }
+
+    async fn list_directory(
+        &self,
+        repo: &str,
+        path: &str,
+        branch: &str,
+    ) -> anyhow::Result<Vec<DirEntry>> {
+        let items = self.get_content(repo, path, branch).await?;
+
+        Ok(items
+            .into_iter()
+            .map(|content| DirEntry {
+                is_dir: content.r#type == "dir",
+                name: content.name,
+                path: content.path,
+            })
+            .collect())
+    }
+
+    async fn list_files(&self, repo: &str, branch: &str) -> anyhow::Result<FileTree> {
+        let tree: Tree = self
+            .octocrab
+            .get(
+                format!("/repos/{}/{}/git/trees/{}", self.owner, repo, branch),
+                Some(&[("recursive", "1")]),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(describe_error(&e)))
+            .with_context(|| format!("failed to fetch the tree of {repo}"))?;
+
+        Ok(FileTree {
+            paths: tree
+                .tree
+                .into_iter()
+                .filter(|entry| entry.kind == "blob")
+                .map(|entry| entry.path)
+                .collect(),
+            truncated: tree.truncated,
+        })
+    }
+
+    async fn create_branch(&self, repo: &str, branch: &str, from: &str) -> anyhow::Result<bool> {
+        let octocrab_repo = self.octocrab.repos(&self.owner, repo);
+
+        if octocrab_repo
+            .get_ref(&Reference::Branch(branch.to_string()))
+            .await
+            .is_ok()
+        {
+            return Ok(false);
+        }
+
+        let from_ref = octocrab_repo
+            .get_ref(&Reference::Branch(from.to_string()))
+            .await
+            .with_context(|| format!("failed to fetch ref to {from} branch"))?;
+
+        octocrab_repo
+            .create_ref(
+                &Reference::Branch(branch.to_string()),
+                match from_ref.object {
+                    Object::Commit { sha, .. } => sha,
+                    Object::Tag { sha, .. } => sha,
+                    _ => panic!("unexpected object type"),
+                },
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn delete_branch(&self, repo: &str, branch: &str) -> anyhow::Result<bool> {
+        let octocrab_repo = self.octocrab.repos(&self.owner, repo);
+        let reference = Reference::Branch(branch.to_string());
+
+        if octocrab_repo.get_ref(&reference).await.is_err() {
+            return Ok(false);
+        }
+
+        octocrab_repo.delete_ref(&reference).await?;
+        Ok(true)
+    }
+
+    async fn create_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        path: &str,
+        message: &str,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        self.octocrab
+            .repos(&self.owner, repo)
+            .create_file(path, message, content)
+            .branch(branch)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn update_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        message: &str,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        self.octocrab
+            .repos(&self.owner, repo)
+            .update_file(&file.path, message, content, &file.sha)
+            .branch(branch)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_file(
+        &self,
+        repo: &str,
+        branch: &str,
+        file: &RemoteFile,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        self.octocrab
+            .repos(&self.owner, repo)
+            .delete_file(&file.path, message, &file.sha)
+            .branch(branch)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn open_pr(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> anyhow::Result<OpenedPr> {
+        match self
+            .octocrab
+            .pulls(&self.owner, repo)
+            .create(title, branch, base)
+            .body(body)
+            .draft(draft)
+            .send()
+            .await
+        {
+            Ok(pr) => Ok(OpenedPr::Created(pull_request(pr))),
+            Err(e) if is_pr_already_exists(&e) => Ok(OpenedPr::AlreadyExists),
+            Err(e) => Err(anyhow::anyhow!(describe_error(&e))),
+        }
+    }
+
+    async fn open_prs(
+        &self,
+        repo: &str,
+        branch: &str,
+        base: Option<&str>,
+    ) -> anyhow::Result<Vec<PullRequest>> {
+        let pulls = self.octocrab.pulls(&self.owner, repo);
+        let mut list = pulls
+            .list()
+            .state(State::Open)
+            .head(format!("{}:{}", self.owner, branch));
+        if let Some(base) = base {
+            list = list.base(base);
+        }
+        let prs = list.send().await?;
+
+        Ok(prs.items.into_iter().map(pull_request).collect())
+    }
+
+    async fn count_open_prs(&self, branch: &str) -> anyhow::Result<usize> {
+        let query = format!("org:{} is:pr is:open head:{}", self.owner, branch);
+        let result = self
+            .octocrab
+            .search()
+            .issues_and_pull_requests(&query)
+            .per_page(1)
+            .send()
+            .await
+            .context("failed to search open PRs")?;
+
+        Ok(result.total_count.unwrap_or_default() as usize)
+    }
+
+    async fn update_pr(
+        &self,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> anyhow::Result<()> {
+        self.octocrab
+            .pulls(&self.owner, repo)
+            .update(number)
+            .title(title)
+            .body(body)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn update_pr_base(&self, repo: &str, number: u64, base: &str) -> anyhow::Result<()> {
+        self.octocrab
+            .pulls(&self.owner, repo)
+            .update(number)
+            .base(base)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn team_exists(&self, team: &str) -> anyhow::Result<bool> {
+        match self.octocrab.teams(&self.owner).get(team).await {
+            Ok(_) => Ok(true),
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.status_code == StatusCode::NOT_FOUND =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e).context("failed to fetch team"),
+        }
+    }
+
+    async fn request_team_reviews(
+        &self,
+        repo: &str,
+        number: u64,
+        teams: &[&str],
+    ) -> anyhow::Result<()> {
+        // The response is the PR, which octocrab's `request_reviews` expects to be a review.
+        let _: serde_json::Value = self
+            .octocrab
+            .post(
+                format!(
+                    "/repos/{}/{}/pulls/{}/requested_reviewers",
+                    self.owner, repo, number
+                ),
+                Some(&serde_json::json!({ "team_reviewers": teams })),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn close_pr(&self, repo: &str, number: u64) -> anyhow::Result<()> {
+        self.octocrab
+            .pulls(&self.owner, repo)
+            .update(number)
+            .state(octocrab::params::pulls::State::Closed)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn can_assign(&self, repo: &str, user: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .octocrab
+            .issues(&self.owner, repo)
+            .check_assignee(user)
+            .await?)
+    }
+
+    async fn add_assignees(&self, repo: &str, number: u64, users: &[&str]) -> anyhow::Result<()> {
+        self.octocrab
+            .issues(&self.owner, repo)
+            .add_assignees(number, users)
+            .await?;
+        Ok(())
+    }
+
+    async fn custom_properties(&self, repo: &str) -> anyhow::Result<Vec<Property>> {
+        let props = self
+            .octocrab
+            .list_custom_properties(&self.owner, repo)
+            .await?;
+
+        Ok(props.into_iter().map(Property::from).collect())
+    }
+}
+
+/// Everything but the unreserved characters of RFC 3986 is encoded in a path segment or query
+/// value, so that e.g. a `#` in a directory or a `+` in a branch name is sent as is.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Percent-encodes each segment of a repo path like `my app/package.json`.
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| utf8_percent_encode(segment, COMPONENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn pull_request(pr: octocrab::models::pulls::PullRequest) -> PullRequest {
+    PullRequest {
+        number: pr.number,
+        base: pr.base.ref_field,
+        title: pr.title,
+        body: pr.body,
+        url: pr.html_url.map(|url| url.to_string()),
+    }
+}
+
 pub async fn get_all<'a, T>(
     octocrab: &'a Octocrab,
     fetch_page: impl Fn(
@@ -139,3 +630,407 @@ pub async fn get_all_repos(
     })
     .await
 }
+
+/// Builds a client authenticated as the installation of a GitHub App in `org`, so that no
+/// long-lived personal access token is needed.
+pub async fn app_installation_client(
+    app_id: u64,
+    private_key: &str,
+    org: &str,
+    api_url: Option<&str>,
+) -> anyhow::Result<Octocrab> {
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())
+        .context("failed to parse app private key")?;
+    let mut builder = Octocrab::builder();
+    if let Some(api_url) = api_url {
+        builder = builder
+            .base_uri(api_url)
+            .context("invalid GITHUB_API_URL")?;
+    }
+    let app = builder.app(AppId(app_id), key).build()?;
+
+    let installation = app
+        .apps()
+        .get_org_installation(org)
+        .await
+        .context("failed to find app installation for org")?;
+
+    Ok(app.installation(installation.id)?)
+}
+
+/// Whether creating a PR failed only because a PR for the branch is already open.
+pub fn is_pr_already_exists(error: &octocrab::Error) -> bool {
+    let octocrab::Error::GitHub { source, .. } = error else {
+        return false;
+    };
+
+    source.status_code.as_u16() == 422
+        && source.errors.iter().flatten().any(|error| {
+            error
+                .get("message")
+                .and_then(|message| message.as_str())
+                .is_some_and(|message| message.starts_with("A pull request already exists"))
+        })
+}
+
+/// Describes an error including the detailed errors GitHub returns e.g. for validation failures.
+pub fn describe_error(error: &octocrab::Error) -> String {
+    let octocrab::Error::GitHub { source, .. } = error else {
+        return error.to_string();
+    };
+
+    let details = source
+        .errors
+        .iter()
+        .flatten()
+        .map(
+            |error| match error.get("message").and_then(|message| message.as_str()) {
+                Some(message) => message.to_string(),
+                None => error.to_string(),
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if details.is_empty() {
+        format!("{} ({})", source.message, source.status_code)
+    } else {
+        format!(
+            "{} ({}): {}",
+            source.message,
+            source.status_code,
+            details.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const CONFIG_PATH: &str = "/repos/KittyCAD/engine/contents/.github/dependabot.yml";
+
+    /// A forge for the `KittyCAD` org that talks to `server` instead of GitHub.
+    fn github(server: &MockServer) -> GitHub {
+        let octocrab = Octocrab::builder()
+            .base_uri(server.uri())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        GitHub::new(octocrab, "KittyCAD", EtagCache::open(None).unwrap())
+    }
+
+    fn content(path: &str, base64: &str) -> serde_json::Value {
+        json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "sha": "3d21ec53a331a6f037a91c368710b99387d012c1",
+            "encoding": "base64",
+            "content": base64,
+            "size": 11,
+            "url": "https://api.github.com/repos/KittyCAD/engine/contents/.github/dependabot.yml",
+            "type": "file",
+            "_links": {
+                "self": "https://api.github.com/repos/KittyCAD/engine/contents/.github/dependabot.yml"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn lists_repos_of_all_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/orgs/KittyCAD/repos"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {
+                    "id": 1,
+                    "name": "engine",
+                    "full_name": "KittyCAD/engine",
+                    "url": "https://api.github.com/repos/KittyCAD/engine",
+                    "default_branch": "main",
+                    "archived": false,
+                    "size": 2048,
+                    "has_pages": false
+                },
+                {
+                    "id": 2,
+                    "name": "empty",
+                    "url": "https://api.github.com/repos/KittyCAD/empty",
+                    "archived": true,
+                    "size": 0,
+                    "has_pages": true
+                }
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/orgs/KittyCAD/repos"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&server)
+            .await;
+
+        let repos = github(&server).list_repos().await.unwrap();
+
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].full_name, "KittyCAD/engine");
+        assert_eq!(repos[0].default_branch.as_deref(), Some("main"));
+        assert!(!repos[0].archived);
+        assert_eq!(repos[1].full_name, "KittyCAD/empty");
+        assert_eq!(repos[1].default_branch, None);
+        assert!(repos[1].archived);
+        assert!(repos[1].has_pages);
+    }
+
+    #[tokio::test]
+    async fn gets_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(CONFIG_PATH))
+            .and(query_param("ref", "main"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(content(".github/dependabot.yml", "dmVyc2lvbjogMgo=")),
+            )
+            .mount(&server)
+            .await;
+
+        let file = github(&server)
+            .get_file("engine", ".github/dependabot.yml", "main")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(file.path, ".github/dependabot.yml");
+        assert_eq!(file.sha, "3d21ec53a331a6f037a91c368710b99387d012c1");
+        assert_eq!(file.content.as_deref(), Some("version: 2\n"));
+    }
+
+    #[tokio::test]
+    async fn encodes_path_and_branch() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/repos/KittyCAD/engine/contents/my%20app/%23docs/package.json",
+            ))
+            .and(query_param("ref", "release+1&2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(content("my app/#docs/package.json", "e30K")),
+            )
+            .mount(&server)
+            .await;
+
+        let file = github(&server)
+            .get_file("engine", "my app/#docs/package.json", "release+1&2")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(file.path, "my app/#docs/package.json");
+        assert_eq!(file.content.as_deref(), Some("{}\n"));
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_none() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(CONFIG_PATH))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "message": "Not Found",
+                "documentation_url": "https://docs.github.com/rest/repos/contents#get-repository-content"
+            })))
+            .mount(&server)
+            .await;
+
+        let file = github(&server)
+            .get_file("engine", ".github/dependabot.yml", "main")
+            .await
+            .unwrap();
+
+        assert!(file.is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_file_fetch_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(CONFIG_PATH))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+                "message": "API rate limit exceeded",
+                "documentation_url": "https://docs.github.com/rest/overview/rate-limits-for-the-rest-api"
+            })))
+            .mount(&server)
+            .await;
+
+        let error = github(&server)
+            .get_file("engine", ".github/dependabot.yml", "main")
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "failed to fetch .github/dependabot.yml of engine: 403 Forbidden"
+        );
+    }
+
+    #[tokio::test]
+    async fn unchanged_file_is_answered_from_etag_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(CONFIG_PATH))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(CONFIG_PATH))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_json(content(".github/dependabot.yml", "dmVyc2lvbjogMgo=")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let github = github(&server);
+
+        for _ in 0..2 {
+            let file = github
+                .get_file("engine", ".github/dependabot.yml", "main")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(file.content.as_deref(), Some("version: 2\n"));
+        }
+    }
+
+    #[tokio::test]
+    async fn creates_pr() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/repos/KittyCAD/engine/pulls"))
+            .and(body_partial_json(json!({
+                "head": "dependabot-config",
+                "base": "main",
+                "draft": true
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "url": "https://api.github.com/repos/KittyCAD/engine/pulls/7",
+                "id": 7007,
+                "number": 7,
+                "title": "Update dependabot config",
+                "html_url": "https://github.com/KittyCAD/engine/pull/7",
+                "head": { "ref": "dependabot-config", "sha": "a1" },
+                "base": { "ref": "main", "sha": "b2" }
+            })))
+            .mount(&server)
+            .await;
+
+        let opened = github(&server)
+            .open_pr(
+                "engine",
+                "dependabot-config",
+                "main",
+                "Update dependabot config",
+                "Generated",
+                true,
+            )
+            .await
+            .unwrap();
+
+        let OpenedPr::Created(pr) = opened else {
+            panic!("expected a created PR, got {opened:?}");
+        };
+        assert_eq!(pr.number, 7);
+        assert_eq!(pr.base, "main");
+        assert_eq!(
+            pr.url.as_deref(),
+            Some("https://github.com/KittyCAD/engine/pull/7")
+        );
+    }
+
+    /// Mounts a 422 response to PR creation with the given validation error.
+    async fn reject_pr(server: &MockServer, message: &str) {
+        Mock::given(method("POST"))
+            .and(path("/repos/KittyCAD/engine/pulls"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "Validation Failed",
+                "errors": [{ "resource": "PullRequest", "code": "custom", "message": message }],
+                "documentation_url": "https://docs.github.com/rest/pulls/pulls#create-a-pull-request"
+            })))
+            .mount(server)
+            .await;
+    }
+
+    async fn open_pr(server: &MockServer) -> anyhow::Result<OpenedPr> {
+        github(server)
+            .open_pr(
+                "engine",
+                "dependabot-config",
+                "main",
+                "Update dependabot config",
+                "Generated",
+                false,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn existing_pr_is_not_an_error() {
+        let server = MockServer::start().await;
+        reject_pr(
+            &server,
+            "A pull request already exists for KittyCAD:dependabot-config.",
+        )
+        .await;
+
+        let opened = open_pr(&server).await.unwrap();
+
+        assert!(matches!(opened, OpenedPr::AlreadyExists), "{opened:?}");
+    }
+
+    #[tokio::test]
+    async fn other_validation_errors_fail_pr_creation() {
+        let server = MockServer::start().await;
+        reject_pr(&server, "No commits between main and dependabot-config").await;
+
+        let error = open_pr(&server).await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Validation Failed (422 Unprocessable Entity): No commits between main and dependabot-config"
+        );
+    }
+
+    #[tokio::test]
+    async fn counts_open_prs_of_org() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .and(query_param(
+                "q",
+                "org:KittyCAD is:pr is:open head:dependabot-config",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total_count": 3,
+                "incomplete_results": false,
+                "items": []
+            })))
+            .mount(&server)
+            .await;
+
+        let count = github(&server)
+            .count_open_prs("dependabot-config")
+            .await
+            .unwrap();
+
+        assert_eq!(count, 3);
+    }
+}
@@ -0,0 +1,92 @@
+/// Matches `text` against a simple glob `pattern`.
+///
+/// Only `*` (any sequence of characters, including none) and `?` (exactly one character) are
+/// supported. This mirrors the wildcard semantics Dependabot uses for dependency names.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was matched against.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns whether any of the `patterns` matches `text`.
+pub fn any_glob_match(patterns: &[String], text: &str) -> bool {
+    patterns.iter().any(|pattern| glob_match(pattern, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_matches_any_sequence() {
+        assert!(glob_match("kcl-*", "kcl-lsp"));
+        assert!(glob_match("kcl-*", "kcl-"));
+        assert!(glob_match("*-app", "modeling-app"));
+        assert!(glob_match("*", "engine"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_match("v?", "v1"));
+        assert!(!glob_match("v?", "v"));
+        assert!(!glob_match("v?", "v10"));
+    }
+
+    #[test]
+    fn multiple_stars_backtrack() {
+        assert!(glob_match("*kcl*", "kittycad-kcl-lib"));
+        assert!(glob_match("*kcl*", "kcl"));
+        assert!(glob_match("a*b*c", "a-b-b-c"));
+        assert!(glob_match("**", "engine"));
+        assert!(!glob_match("*kcl*", "kittycad"));
+        assert!(!glob_match("a*b*c", "a-c-b"));
+    }
+
+    #[test]
+    fn empty_pattern_and_text() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "engine"));
+        assert!(!glob_match("engine", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn literal_matches_exactly() {
+        assert!(glob_match("engine", "engine"));
+        assert!(!glob_match("engine", "engines"));
+        assert!(!glob_match("engine", "Engine"));
+        assert!(!glob_match("engine", "website"));
+    }
+
+    #[test]
+    fn any_of_patterns() {
+        let patterns = ["kcl-*".to_string(), "website".to_string()];
+
+        assert!(any_glob_match(&patterns, "kcl-lsp"));
+        assert!(any_glob_match(&patterns, "website"));
+        assert!(!any_glob_match(&patterns, "engine"));
+        assert!(!any_glob_match(&[], "engine"));
+    }
+}
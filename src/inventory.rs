@@ -0,0 +1,134 @@
+use crate::forge::Forge;
+use crate::github::AssetLevel;
+use crate::{Ecosystem, manifest_directory};
+use anyhow::Context;
+use indexmap::IndexMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Writes a CSV with one row per detected manifest, independent of config generation.
+pub async fn write_inventory(
+    forge: &impl Forge,
+    ecosystems: &IndexMap<String, Vec<(String, Ecosystem)>>,
+    level_property: &str,
+    path: &str,
+) -> anyhow::Result<()> {
+    let mut levels = IndexMap::new();
+    for full_name in ecosystems.keys() {
+        let (_, repo) = full_name
+            .split_once('/')
+            .context("full name must contain the owner")?;
+        let props = forge.custom_properties(repo).await?;
+        levels.insert(
+            full_name.clone(),
+            AssetLevel::get_from_props(&props, level_property),
+        );
+    }
+
+    let mut writer = BufWriter::new(File::create(path).context("failed to create inventory file")?);
+    writeln!(writer, "repo,ecosystem,directory,asset-level")?;
+    for row in inventory_rows(ecosystems, &levels) {
+        writeln!(
+            writer,
+            "{}",
+            row.iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(",")
+        )?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// One `[repo, ecosystem, directory, asset-level]` row per detected manifest.
+fn inventory_rows(
+    ecosystems: &IndexMap<String, Vec<(String, Ecosystem)>>,
+    levels: &IndexMap<String, Option<AssetLevel>>,
+) -> Vec<[String; 4]> {
+    ecosystems
+        .iter()
+        .flat_map(|(full_name, manifests)| {
+            let level = levels
+                .get(full_name)
+                .copied()
+                .flatten()
+                .map(|level| level.to_string())
+                .unwrap_or_default();
+
+            manifests.iter().map(move |(path, ecosystem)| {
+                [
+                    full_name.clone(),
+                    ecosystem.to_string(),
+                    manifest_directory(path),
+                    level.clone(),
+                ]
+            })
+        })
+        .collect()
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_per_manifest() {
+        let ecosystems = IndexMap::from([
+            (
+                "KittyCAD/engine".to_string(),
+                vec![
+                    (
+                        "/repositories/1/contents/Cargo.toml".to_string(),
+                        Ecosystem::Cargo,
+                    ),
+                    (
+                        "/repositories/1/contents/web/package.json".to_string(),
+                        Ecosystem::Npm,
+                    ),
+                ],
+            ),
+            (
+                "KittyCAD/docs".to_string(),
+                vec![(
+                    "/repositories/2/contents/requirements.txt".to_string(),
+                    Ecosystem::Pip,
+                )],
+            ),
+        ]);
+        let levels = IndexMap::from([
+            (
+                "KittyCAD/engine".to_string(),
+                Some(AssetLevel::ResearchNDevelopment),
+            ),
+            ("KittyCAD/docs".to_string(), None),
+        ]);
+
+        assert_eq!(
+            inventory_rows(&ecosystems, &levels),
+            [
+                ["KittyCAD/engine", "cargo", "/", "Research & Development"],
+                ["KittyCAD/engine", "npm", "/web", "Research & Development"],
+                ["KittyCAD/docs", "pip", "/", ""],
+            ]
+            .map(|row| row.map(str::to_string))
+        );
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("Production"), "Production");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -1,32 +1,76 @@
+mod audit;
+mod cache;
+mod cargo;
+mod config;
+mod cron;
 mod dependabot;
+mod etag;
+#[cfg(test)]
+mod fake_forge;
+mod forge;
 mod github;
+mod glob;
+mod inventory;
+mod npm;
+mod policy;
+mod prompt;
+mod ratelimit;
+mod report;
+mod schema;
+mod state;
+mod throttle;
+mod tree;
 
 use crate::dependabot::Registry;
 use anyhow::Context;
 use argh::FromArgs;
-use dependabot::{Cooldown, DependabotConfig, Group, Schedule, Update, UpdateOverride};
-use github::{AssetLevel, CustomPropertyExt, get_all, get_all_repos};
-use indexmap::IndexMap;
-use indicatif::ProgressIterator;
+use chrono::{DateTime, Utc};
+use config::RunConfig;
+use dependabot::{DependabotConfig, Update, UpdateOverride};
+use etag::EtagCache;
+use forge::{Forge, OpenedPr, RemoteFile, Repo};
+use futures::{FutureExt, StreamExt, future, stream};
+use github::{AssetLevel, GitHub, app_installation_client, get_all};
+use glob::any_glob_match;
+use indexmap::{IndexMap, IndexSet};
+use indicatif::{ProgressBar, ProgressStyle};
 use octocrab::Octocrab;
-use octocrab::models::repos::{Content, Object};
-use octocrab::models::{Code, Repository};
-use octocrab::params::State;
-use octocrab::params::repos::Reference;
+use octocrab::models::Code;
+use policy::{Policy, PolicyDefaults};
+use prompt::{Decision, Prompt};
+use report::{NdjsonReport, RepoReport, RepoStatus, RunSummary, ScheduleSlots};
 use serde::{Deserialize, Serialize};
+use state::StateFile;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::str::FromStr;
 use std::time::Duration;
 use std::{env, fs};
+use throttle::{PrThrottle, WriteLimit, Writes};
+use tokio::sync::Semaphore;
 use tokio::time::sleep;
 
 #[derive(FromArgs)]
 /// Check Dependabot status for all repositories in an organization
 struct Args {
     // GitHub organization name
-    #[argh(positional, description = "organization name")]
+    #[argh(
+        positional,
+        default = "String::new()",
+        description = "organization name, can also be set via --config"
+    )]
     org: String,
+    #[argh(
+        option,
+        description = "ID of a GitHub App to authenticate as instead of GH_TOKEN, defaults to GH_APP_ID"
+    )]
+    app_id: Option<u64>,
+    #[argh(
+        option,
+        description = "path to the GitHub App private key, defaults to GH_APP_PRIVATE_KEY_PATH"
+    )]
+    app_private_key: Option<String>,
     #[argh(option, description = "optional cache to use for ecosystems")]
     ecosystems_cache: Option<String>,
     #[argh(option, description = "optional dependabot_overrides file path")]
@@ -44,511 +88,2127 @@ struct Args {
     #[argh(option, description = "limit to repos")]
     repo: Vec<String>,
 
+    #[argh(
+        option,
+        description = "only process repos pushed after this RFC3339 timestamp, e.g. 2025-01-01T00:00:00Z"
+    )]
+    since: Option<DateTime<Utc>>,
+
+    #[argh(
+        option,
+        description = "only process repos whose name matches this glob (repeatable)"
+    )]
+    include: Vec<String>,
+
+    #[argh(
+        option,
+        description = "skip repos whose name matches this glob, takes precedence over --include and --repo (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[argh(
+        option,
+        description = "only process repos with this repository-level, e.g. \"Production\" (repeatable, defaults to all but Playground)"
+    )]
+    level: Vec<AssetLevel>,
+
+    #[argh(
+        option,
+        default = "String::from(\"repository-level\")",
+        description = "name of the custom property holding the asset level of a repo"
+    )]
+    level_property: String,
+
+    #[argh(
+        option,
+        description = "asset level of repos without the custom property, e.g. \"Corporate\" (by default they are skipped)"
+    )]
+    default_level: Option<AssetLevel>,
+
     #[argh(switch, description = "whether to print verbose output")]
     verbose: bool,
 
     #[argh(switch, description = "only process repos with existing PRs")]
     only_existing: bool,
+
+    #[argh(
+        switch,
+        description = "only write configs whose set of ecosystems and directories differs from the existing config"
+    )]
+    only_changed_ecosystems: bool,
+
+    #[argh(
+        switch,
+        description = "omit the cooldown of all generated updates, e.g. to catch up after a freeze"
+    )]
+    no_cooldown: bool,
+
+    #[argh(
+        switch,
+        description = "generate updates for ecosystems Dependabot only supports in beta, like pub"
+    )]
+    enable_beta_ecosystems: bool,
+
+    #[argh(
+        option,
+        description = "never generate updates for this ecosystem, e.g. docker, can be repeated"
+    )]
+    exclude_ecosystem: Vec<Ecosystem>,
+
+    #[argh(
+        option,
+        description = "stream one JSON record per processed repo to this file, - for stdout"
+    )]
+    ndjson: Option<String>,
+
+    #[argh(
+        switch,
+        description = "validate generated configs against the dependabot JSON schema"
+    )]
+    schema_validate: bool,
+
+    #[argh(switch, description = "skip repos that only host a GitHub Pages site")]
+    skip_pages: bool,
+
+    #[argh(
+        option,
+        default = "1",
+        description = "number of repos to process concurrently"
+    )]
+    concurrency: usize,
+
+    #[argh(
+        option,
+        default = "1",
+        description = "number of repos to create or update PRs for concurrently, limited separately as writes are rate-limited more strictly"
+    )]
+    write_concurrency: usize,
+
+    #[argh(
+        option,
+        default = "3",
+        description = "collapse the update blocks of an ecosystem into a single block with directories once a repo has more than this many"
+    )]
+    collapse_directories: usize,
+
+    #[argh(
+        switch,
+        description = "only print the generated configs, never create branches or PRs"
+    )]
+    print_only: bool,
+
+    #[argh(
+        option,
+        default = "50",
+        description = "warn if more repos than this share the exact same schedule slot"
+    )]
+    max_repos_per_slot: usize,
+
+    #[argh(
+        option,
+        description = "only write a CSV inventory of all detected manifests to this path and exit"
+    )]
+    inventory: Option<String>,
+
+    #[argh(
+        switch,
+        description = "only print which selected repos with detected ecosystems have a dependabot config and exit"
+    )]
+    audit: bool,
+
+    #[argh(
+        switch,
+        description = "open PRs removing generated configs of repos without any detected ecosystems"
+    )]
+    remove_orphaned: bool,
+
+    #[argh(
+        switch,
+        description = "close open bot PRs and delete the bot branch of archived repos"
+    )]
+    cleanup_archived: bool,
+
+    #[argh(
+        switch,
+        description = "process archived repos as well, GitHub rejects writes to them until they are unarchived"
+    )]
+    include_archived: bool,
+
+    #[argh(
+        switch,
+        description = "replace existing dependabot configs that were not generated by this tool"
+    )]
+    takeover: bool,
+
+    #[argh(
+        switch,
+        description = "show the change of each config and ask on stdin before creating or updating its PR"
+    )]
+    interactive: bool,
+
+    #[argh(
+        switch,
+        description = "open new config PRs as drafts, existing PRs keep their draft state"
+    )]
+    draft: bool,
+
+    #[argh(
+        switch,
+        description = "fail if any committed config differs from the generated one, never creates branches or PRs"
+    )]
+    check: bool,
+
+    #[argh(
+        option,
+        description = "only validate the dependabot config at this path like generated configs and exit"
+    )]
+    validate_only: Option<String>,
+
+    #[argh(
+        switch,
+        description = "only print the detected ecosystems as JSON and exit, respects --repo, --include and --exclude"
+    )]
+    dump_ecosystems: bool,
+
+    #[argh(
+        option,
+        description = "stop opening PRs in further repos once the org has this many open bot PRs"
+    )]
+    max_open_prs: Option<usize>,
+
+    #[argh(
+        option,
+        description = "pause PR creation after this many opened PRs, see --pr-batch-pause"
+    )]
+    pr_batch_size: Option<usize>,
+
+    #[argh(
+        option,
+        default = "60",
+        description = "seconds to pause between batches of PRs"
+    )]
+    pr_batch_pause: u64,
+
+    #[argh(
+        option,
+        default = "Detection::Search",
+        description = "how to detect ecosystems: search (code search, default) or tree (walk each repo's git tree)"
+    )]
+    detection: Detection,
+
+    #[argh(
+        option,
+        description = "stop after this many repos had their PR created or updated"
+    )]
+    limit: Option<usize>,
+
+    #[argh(
+        option,
+        description = "remember generated configs in this file and skip repos whose default branch already has them, unless --force-new is passed"
+    )]
+    state: Option<String>,
+
+    #[argh(
+        switch,
+        description = "check that every generated config parses back into the same config"
+    )]
+    self_check: bool,
+
+    #[argh(
+        option,
+        description = "assign the created config PRs to this user (repeatable)"
+    )]
+    assignee: Vec<String>,
+
+    #[argh(
+        option,
+        description = "request reviews of the created config PRs from this team of the org (repeatable)"
+    )]
+    team_reviewer: Vec<String>,
+
+    #[argh(
+        switch,
+        description = "emit logs as JSON lines, also enabled by LOG_FORMAT=json"
+    )]
+    json_logs: bool,
+
+    #[argh(
+        option,
+        description = "write the generated configs to <dir>/<repo>/.github/dependabot.yml instead of creating PRs"
+    )]
+    output_dir: Option<String>,
+
+    #[argh(
+        option,
+        description = "path of the dependabot config within the repos, replaces .github/dependabot.yml and .github/dependabot.yaml"
+    )]
+    dependabot_filename: Option<String>,
+
+    #[argh(switch, description = "abort the run at the first repo that fails")]
+    fail_fast: bool,
+
+    #[argh(
+        switch,
+        description = "continue with the next repo when a repo fails and exit nonzero at the end (default)"
+    )]
+    keep_going: bool,
+
+    #[argh(
+        option,
+        description = "TOML file with the org, branch, paths, assignees and override sections, command line flags take precedence"
+    )]
+    config: Option<String>,
+
+    #[argh(
+        option,
+        description = "branch the generated config is committed to, defaults to ciso/update-dependabot"
+    )]
+    branch: Option<String>,
+}
+
+impl Args {
+    fn bot_branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or("ciso/update-dependabot")
+    }
+
+    /// Whether the detected ecosystems cover the whole org and may be cached for later runs. The
+    /// tree detection only walks the selected repos, so a filtered run would leave every other
+    /// repo without ecosystems in the cache.
+    fn detects_whole_org(&self) -> bool {
+        self.detection == Detection::Search
+            || (self.repo.is_empty()
+                && self.include.is_empty()
+                && self.exclude.is_empty()
+                && self.since.is_none())
+    }
 }
 
 type Registries = IndexMap<String, Registry>;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
 struct DependabotOverrides {
+    defaults: PolicyDefaults,
     registries: IndexMap<String, Registries>,
     updates: IndexMap<String, Vec<UpdateOverride>>,
+    /// Branches by repo that each get their own copy of the updates, e.g. for release lines.
+    target_branches: IndexMap<String, Vec<String>>,
+}
+
+impl DependabotOverrides {
+    /// Reads the overrides from a YAML file if it ends in `.yaml` or `.yml`, from TOML otherwise.
+    fn load(path: &str) -> anyhow::Result<DependabotOverrides> {
+        let mut file = File::open(path).context("failed to open file")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        if path.ends_with(".yaml") || path.ends_with(".yml") {
+            serde_yaml_ng::from_str(&contents).context("failed to read overrides YAML from file")
+        } else {
+            toml::from_str(&contents).context("failed to read overrides TOML from file")
+        }
+    }
+
+    /// Validates the per-repo overrides, so that bad input fails the run before any repo is
+    /// processed. A repo may have at most one override per ecosystem and one for all of them.
+    fn validate_updates(&self) -> anyhow::Result<()> {
+        for (repo, updates) in &self.updates {
+            let mut ecosystems = IndexSet::new();
+            for update in updates {
+                anyhow::ensure!(
+                    ecosystems.insert(update.package_ecosystem.as_deref()),
+                    "repo {} has more than one override for ecosystem {}",
+                    repo,
+                    update.package_ecosystem.as_deref().unwrap_or("*")
+                );
+                if let Some(cooldown) = &update.cooldown {
+                    cooldown.validate().with_context(|| {
+                        format!(
+                            "invalid cooldown override for repo {} and ecosystem {}",
+                            repo,
+                            update.package_ecosystem.as_deref().unwrap_or("*")
+                        )
+                    })?;
+                }
+                if let Some(schedule) = &update.schedule {
+                    schedule.validate().with_context(|| {
+                        format!(
+                            "invalid schedule override for repo {} and ecosystem {}",
+                            repo,
+                            update.package_ecosystem.as_deref().unwrap_or("*")
+                        )
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::init();
+    let mut args: Args = argh::from_env();
+    init_logger(args.json_logs || env::var("LOG_FORMAT").is_ok_and(|format| format == "json"));
 
-    let args: Args = argh::from_env();
-    let gh_token = env::var("GH_TOKEN").context("GitHub token not set")?;
+    // Linting a local file needs neither an org nor credentials.
+    if let Some(path) = &args.validate_only {
+        return validate_file(path);
+    }
 
-    let octocrab = Octocrab::builder()
-        .user_access_token(gh_token)
-        .build()
-        .expect("Failed to create GitHub client");
+    let config = match &args.config {
+        Some(path) => RunConfig::load(path)?,
+        None => RunConfig::default(),
+    };
+    config.apply_to(&mut args)?;
+
+    anyhow::ensure!(
+        args.concurrency > 0 && args.write_concurrency > 0,
+        "--concurrency and --write-concurrency must be at least 1"
+    );
+    anyhow::ensure!(
+        args.pr_batch_size != Some(0),
+        "--pr-batch-size must be at least 1"
+    );
+    anyhow::ensure!(args.limit != Some(0), "--limit must be at least 1");
+    anyhow::ensure!(
+        !args.interactive || args.create_pr,
+        "--interactive requires --create-pr"
+    );
+    anyhow::ensure!(
+        args.dependabot_filename.as_deref().is_none_or(|filename| {
+            filename
+                .split('/')
+                .all(|segment| !matches!(segment, "" | "." | ".."))
+        }),
+        "--dependabot-filename must be a relative path within the repo, e.g. .github/dependabot.yml"
+    );
+    anyhow::ensure!(
+        !(args.include_archived && args.cleanup_archived),
+        "--include-archived and --cleanup-archived are mutually exclusive"
+    );
+    // The NDJSON records must be the only output on stdout.
+    anyhow::ensure!(
+        args.ndjson.as_deref() != Some("-")
+            || !(args.verbose || args.print_only || args.interactive),
+        "--ndjson - cannot be combined with --verbose, --print-only or --interactive, which print to stdout"
+    );
+    anyhow::ensure!(
+        !(args.fail_fast && args.keep_going),
+        "--fail-fast and --keep-going are mutually exclusive"
+    );
+
+    let octocrab = build_client(&args).await?;
+
+    // Without a cache file to read, the ecosystems are detected anew.
+    let searching = args.detection == Detection::Search
+        && match &args.ecosystems_cache {
+            Some(cache) => !fs::exists(cache)?,
+            None => true,
+        };
+    let start_limits = ratelimit::log_rate_limits(&octocrab, searching).await;
 
-    let dependabot_overrides = if let Some(dependabot_overrides_file) = &args.dependabot_overrides {
-        let mut file = File::open(dependabot_overrides_file).context("failed to open file")?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
+    let result = run(args, config.overrides, octocrab.clone()).await;
+
+    if let Some(start_limits) = &start_limits {
+        ratelimit::log_consumed(&octocrab, start_limits).await;
+    }
+    result
+}
 
-        let dependabot_overrides: DependabotOverrides =
-            toml::from_str(&contents).context("failed to read overrides TOML from file")?;
-        dependabot_overrides
+/// Runs the validations of generated configs and the schema check on a local dependabot.yml and
+/// prints every problem.
+fn validate_file(path: &str) -> anyhow::Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    // The schema check sees the file as written, the typed config drops unknown keys.
+    let raw = serde_yaml_ng::from_str::<serde_json::Value>(&content)
+        .with_context(|| format!("failed to parse {path}"))?;
+    let config = serde_yaml_ng::from_str::<DependabotConfig>(&content)
+        .with_context(|| format!("{path} is not a valid dependabot config"))?;
+
+    let mut errors = config.validation_errors();
+    if let Err(e) = schema::validate_instance(&raw) {
+        errors.push(e);
+    }
+
+    if errors.is_empty() {
+        println!("{path} is valid");
+        return Ok(());
+    }
+
+    for error in &errors {
+        println!("{path}: {error:#}");
+    }
+    anyhow::bail!("found {} problems in {}", errors.len(), path)
+}
+
+/// Builds the GitHub client from the app credentials or `GH_TOKEN`. `GITHUB_API_URL` points it at
+/// another API, e.g. GitHub Enterprise Server or a mock server.
+async fn build_client(args: &Args) -> anyhow::Result<Octocrab> {
+    let api_url = env::var("GITHUB_API_URL").ok();
+    let app_id = match &args.app_id {
+        Some(app_id) => Some(*app_id),
+        None => env::var("GH_APP_ID")
+            .ok()
+            .map(|app_id| app_id.parse())
+            .transpose()
+            .context("GH_APP_ID must be a number")?,
+    };
+    let app_private_key = args
+        .app_private_key
+        .clone()
+        .or_else(|| env::var("GH_APP_PRIVATE_KEY_PATH").ok());
+
+    if let (Some(app_id), Some(app_private_key)) = (app_id, app_private_key) {
+        let private_key =
+            fs::read_to_string(&app_private_key).context("failed to read app private key")?;
+        app_installation_client(app_id, &private_key, &args.org, api_url.as_deref())
+            .await
+            .context("failed to authenticate as GitHub App")
     } else {
-        DependabotOverrides {
-            registries: Default::default(),
-            updates: Default::default(),
+        let gh_token = env::var("GH_TOKEN").context("GitHub token not set")?;
+
+        let mut builder = Octocrab::builder();
+        if let Some(api_url) = &api_url {
+            builder = builder
+                .base_uri(api_url.as_str())
+                .context("invalid GITHUB_API_URL")?;
         }
+        Ok(builder
+            .user_access_token(gh_token)
+            .build()
+            .expect("Failed to create GitHub client"))
+    }
+}
+
+/// Generates and writes the configs of all selected repos with the given client.
+async fn run(
+    args: Args,
+    config_overrides: DependabotOverrides,
+    octocrab: Octocrab,
+) -> anyhow::Result<()> {
+    let dependabot_overrides = match &args.dependabot_overrides {
+        Some(dependabot_overrides_file) => DependabotOverrides::load(dependabot_overrides_file)?,
+        None => config_overrides,
     };
 
+    dependabot_overrides
+        .defaults
+        .validate_header()
+        .context("invalid header")?;
+    if let Some(schedule) = &dependabot_overrides.defaults.schedule {
+        schedule.validate().context("invalid default schedule")?;
+    }
+    if let Some(cooldown) = &dependabot_overrides.defaults.cooldown {
+        cooldown.validate().context("invalid default cooldown")?;
+    }
+    dependabot_overrides.validate_updates()?;
+
+    let forge = GitHub::new(
+        octocrab.clone(),
+        &args.org,
+        EtagCache::open(args.ecosystems_cache.as_deref())?,
+    );
+    let repos = forge.list_repos().await?;
+
+    let selected_repos = repos
+        .iter()
+        .filter(|repo| {
+            // Filter out archived repos, unless they are included via CLI
+            // Filter out repos that are not enabled via CLI
+            // Filter out repos that are excluded via CLI, even if they are enabled
+            // Filter out repos that were not pushed to since the given date
+            !((repo.archived && !args.include_archived)
+                || (!args.repo.is_empty() && !args.repo.contains(&repo.name))
+                || (!args.include.is_empty() && !any_glob_match(&args.include, &repo.name))
+                || any_glob_match(&args.exclude, &repo.name)
+                || args
+                    .since
+                    .is_some_and(|since| repo.pushed_at.is_none_or(|pushed_at| pushed_at <= since)))
+        })
+        .collect::<Vec<_>>();
+
     let ecosystems = if let Some(ecosystem_cache) = &args.ecosystems_cache {
         if fs::exists(ecosystem_cache)? {
             let file = File::open(ecosystem_cache).context("failed to open file")?;
             serde_json::from_reader(&file).context("failed to read JSON file")?
         } else {
-            let ecosystems = find_ecosystems(&octocrab).await?;
-            let file = File::create(ecosystem_cache).context("failed to create file")?;
-            serde_json::to_writer(&file, &ecosystems).context("failed to write JSON to file")?;
+            let ecosystems =
+                detect_ecosystems(&octocrab, &forge, &args, &repos, &selected_repos).await?;
+            if args.detects_whole_org() {
+                // Detection finishes before any repo is processed, so this is the only write.
+                cache::write_atomically(ecosystem_cache, &serde_json::to_string(&ecosystems)?)
+                    .context("failed to write ecosystems cache")?;
+            } else {
+                log::warn!(
+                    "Not writing the ecosystems cache, as --detection tree only walked the selected repos"
+                );
+            }
             ecosystems
         }
     } else {
-        find_ecosystems(&octocrab).await?
+        detect_ecosystems(&octocrab, &forge, &args, &repos, &selected_repos).await?
     };
+    // Dumped before any post-processing, which would hide how the detection split the paths.
+    if args.dump_ecosystems {
+        let ecosystems = ecosystems
+            .iter()
+            .filter(|(full_name, _)| {
+                let name = full_name
+                    .split_once('/')
+                    .map_or(full_name.as_str(), |(_, name)| name);
+                (args.repo.is_empty() || args.repo.iter().any(|repo| repo == name))
+                    && (args.include.is_empty() || any_glob_match(&args.include, name))
+                    && !any_glob_match(&args.exclude, name)
+            })
+            .collect::<IndexMap<_, _>>();
+        println!("{}", serde_json::to_string_pretty(&ecosystems)?);
+        return Ok(());
+    }
 
-    let repos = get_all_repos(&octocrab, &args.org)
-        .await
-        .context("failed to fetch repos")?;
+    let ecosystems = drop_malformed_manifests(ecosystems);
+    // The cache holds the detection of the whole org, but only the manifests of the selected
+    // repos are fetched to refine it.
+    let ecosystems = select_ecosystems(ecosystems, &selected_repos);
+    let ecosystems =
+        cargo::expand_virtual_workspaces(&forge, &selected_repos, ecosystems, args.concurrency)
+            .await?;
+    let ecosystems = npm::skip_dependencyless_packages(&forge, &selected_repos, ecosystems).await?;
+
+    if let Some(inventory) = &args.inventory {
+        inventory::write_inventory(&forge, &ecosystems, &args.level_property, inventory).await?;
+        return Ok(());
+    }
 
     if repos.is_empty() {
         log::warn!("No repositories found.");
         return Ok(());
     }
 
-    let default_schedule = Schedule {
-        interval: "weekly".to_string(),
-        day: Some("saturday".to_string()),
-        time: None, // Some("03:00".to_string()),
-        timezone: Some("America/Los_Angeles".to_string()),
-        ..Schedule::default()
+    let mut ndjson = NdjsonReport::open(args.ndjson.as_deref())?;
+    let mut schedule_slots = ScheduleSlots::default();
+
+    if args.audit {
+        audit::print_audit(
+            &forge,
+            &selected_repos,
+            &ecosystems,
+            &args.level,
+            &args.level_property,
+            args.default_level,
+            args.dependabot_filename.as_deref(),
+        )
+        .await?;
+        forge.save_etags()?;
+        return Ok(());
+    }
+
+    // The progress bar would draw over the questions of --interactive.
+    let progress = if args.interactive {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(selected_repos.len() as u64)
+    }
+    .with_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} (ETA {eta}) {wide_msg}",
+        )?
+        .progress_chars("=> "),
+    );
+    let state = StateFile::open(args.state.as_deref())?;
+    let new_pr_budget = match args.max_open_prs {
+        Some(max_open_prs) => {
+            let open_prs = forge.count_open_prs(args.bot_branch()).await?;
+            log::info!(
+                "{} of at most {} bot PRs are open in the org",
+                open_prs,
+                max_open_prs
+            );
+            Some(max_open_prs.saturating_sub(open_prs))
+        }
+        None => None,
     };
-    let open_pull_requests_limit = Some(5);
-    let default_groups = IndexMap::from([
-        (
-            "security".to_string(),
-            Group {
-                applies_to: Some("security-updates".to_string()),
-                update_types: Some(vec!["minor".to_string(), "patch".to_string()]),
-                exclude_patterns: Some(vec![
-                    "ezpz".to_string(),
-                    "kcl*".to_string(),
-                    "kittycad*".to_string(),
-                ]),
-                ..Group::default()
-            },
-        ),
-        (
-            "patch".to_string(),
-            Group {
-                applies_to: Some("version-updates".to_string()),
-                update_types: Some(vec!["patch".to_string()]),
-                exclude_patterns: Some(vec![
-                    "ezpz".to_string(),
-                    "kcl*".to_string(),
-                    "kittycad*".to_string(),
-                ]),
-                ..Group::default()
-            },
-        ),
-        // No major groups, to avoid grouping of them.
-        (
-            "minor".to_string(),
-            Group {
-                applies_to: Some("version-updates".to_string()),
-                update_types: Some(vec!["minor".to_string(), "patch".to_string()]),
-                exclude_patterns: Some(vec![
-                    "ezpz".to_string(),
-                    "kcl*".to_string(),
-                    "kittycad*".to_string(),
-                ]),
-                ..Group::default()
-            },
-        ),
-        // Group kcl updates together. There are frequently API-breaking changes
-        // that require manual updates.
-        (
-            "kcl".to_string(),
-            Group {
-                applies_to: Some("version-updates".to_string()),
-                patterns: Some(vec!["ezpz".to_string(), "kcl*".to_string()]),
-                ..Group::default()
-            },
-        ),
-    ]);
-
-    let default_cooldown = Cooldown {
-        default_days: Some(7),
-        exclude: Some(vec![
-            "ezpz".to_string(),
-            "*kcl*".to_string(),
-            "*zoo*".to_string(),
-            "*kittycad*".to_string(),
-        ]),
-        ..Cooldown::default()
+    let writes = Writes {
+        permits: Semaphore::new(args.write_concurrency),
+        pr_throttle: PrThrottle::new(args.pr_batch_size, Duration::from_secs(args.pr_batch_pause)),
+        limit: WriteLimit::new(args.limit),
+        new_prs: WriteLimit::new(new_pr_budget),
+        prompt: Prompt::new(args.interactive),
     };
 
-    for repo in repos.iter().progress() {
-        // Filter out archived repos
-        // Filter out repos that are not enabled via CLI
-        if repo.archived.unwrap_or(false)
-            || (!args.repo.is_empty() && !args.repo.contains(&repo.name))
-        {
+    let mut reports = stream::iter(selected_repos)
+        // Don't start further repos once the limit is reached, repos already in flight are
+        // skipped when they get to writing.
+        .take_while(|_| future::ready(!writes.limit.reached() && !writes.prompt.aborted()))
+        .map(|repo| {
+            // With concurrency this is the most recently started repo.
+            progress.set_message(repo.name.clone());
+            process_repo(
+                &forge,
+                &args,
+                &dependabot_overrides,
+                &ecosystems,
+                &writes,
+                &state,
+                repo,
+            )
+            .map(move |report| (repo, report))
+        })
+        .buffer_unordered(args.concurrency);
+
+    let mut drifted = vec![];
+    let mut failed = vec![];
+    let mut summary = RunSummary::default();
+
+    // A single repo failing, e.g. because of a transient API error, must not abort the whole run.
+    while let Some((repo, report)) = reports.next().await {
+        progress.inc(1);
+        let report = match report {
+            Ok(report) => report,
+            Err(e) if args.fail_fast => {
+                progress.abandon();
+                summary.add_failure();
+                ndjson.write_failure(&repo.name, &e)?;
+                summary.print();
+                state.save()?;
+                forge.save_etags()?;
+                return Err(e.context(format!("failed to process {}", repo.name)));
+            }
+            Err(e) => {
+                log::error!("Failed to process {}: {:#}", repo.name, e);
+                summary.add_failure();
+                failed.push(repo.name.clone());
+                ndjson.write_failure(&repo.name, &e)?;
+                continue;
+            }
+        };
+        summary.add(&report);
+        schedule_slots.add(&report);
+        if report.status == RepoStatus::Drifted {
+            drifted.push(report.repo.to_string());
+        }
+        ndjson.write(&report)?;
+    }
+    if writes.limit.reached() {
+        log::info!("Stopped after reaching --limit");
+        progress.abandon_with_message("--limit reached");
+    } else if writes.prompt.aborted() {
+        log::info!("Stopped as the run was aborted");
+        progress.abandon_with_message("aborted");
+    } else {
+        progress.finish();
+    }
+
+    if args.cleanup_archived {
+        let archived_repos = repos.iter().filter(|repo| {
+            repo.archived
+                && (args.repo.is_empty() || args.repo.contains(&repo.name))
+                && (args.include.is_empty() || any_glob_match(&args.include, &repo.name))
+                && !any_glob_match(&args.exclude, &repo.name)
+        });
+        for repo in archived_repos {
+            if let Err(e) = cleanup_archived(&forge, repo, args.bot_branch(), !args.create_pr).await
+            {
+                log::error!("Failed to clean up archived repo {}: {:#}", repo.name, e);
+            }
+        }
+    }
+
+    schedule_slots.warn_overloaded(args.max_repos_per_slot);
+    summary.print();
+    state.save()?;
+    forge.save_etags()?;
+
+    if !failed.is_empty() {
+        failed.sort();
+        anyhow::bail!(
+            "failed to process {} repos:\n{}",
+            failed.len(),
+            failed.join("\n")
+        );
+    }
+
+    if !drifted.is_empty() {
+        drifted.sort();
+        anyhow::bail!(
+            "dependabot config of {} repos drifted from the generated config:\n{}",
+            drifted.len(),
+            drifted.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+/// Configures env_logger, optionally emitting one JSON object per line for log pipelines.
+fn init_logger(json: bool) {
+    let mut builder = env_logger::Builder::from_default_env();
+    if json {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": Utc::now().to_rfc3339(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{line}")
+        });
+    }
+    builder.init();
+}
+
+/// Generates the dependabot config for a single repo and creates or updates its PR.
+async fn process_repo<'a>(
+    forge: &impl Forge,
+    args: &Args,
+    dependabot_overrides: &DependabotOverrides,
+    ecosystems: &IndexMap<String, Vec<(String, Ecosystem)>>,
+    writes: &Writes,
+    state: &StateFile,
+    repo: &'a Repo,
+) -> anyhow::Result<RepoReport<'a>> {
+    // Empty repos have no commits, so there is neither a branch to open a PR against nor
+    // anything to configure.
+    if repo.size == Some(0) || repo.default_branch.is_none() {
+        log::info!("Skipping repo {} as it is empty", repo.name);
+        return Ok(RepoReport::skipped(&repo.name, None, "empty repository"));
+    }
+
+    if args.skip_pages && is_pages_only(forge, repo).await? {
+        log::info!(
+            "Skipping repo {} as it only hosts a GitHub Pages site",
+            repo.name
+        );
+        return Ok(RepoReport::skipped(&repo.name, None, "GitHub Pages site"));
+    }
+
+    let props = forge.custom_properties(&repo.name).await?;
+
+    let repo_level =
+        AssetLevel::get_from_props(&props, &args.level_property).or(args.default_level);
+
+    if args.level.is_empty() {
+        if repo_level.is_none() || repo_level == Some(AssetLevel::Playground) {
+            log::debug!("Skipping repo {} as it is a playground repo", repo.name);
+            return Ok(RepoReport::skipped(
+                &repo.name,
+                repo_level,
+                "playground or missing repository-level",
+            ));
+        }
+    } else if !repo_level.is_some_and(|level| args.level.contains(&level)) {
+        log::debug!(
+            "Skipping repo {} as its level is not selected via --level",
+            repo.name
+        );
+        return Ok(RepoReport::skipped(
+            &repo.name,
+            repo_level,
+            "repository-level not selected",
+        ));
+    }
+
+    let level = repo_level.expect("repos without a level are skipped");
+    let mut policy = Policy::for_level(level, &dependabot_overrides.defaults);
+    policy.no_cooldown = args.no_cooldown;
+    policy.enable_beta_ecosystems = args.enable_beta_ecosystems;
+    policy.excluded_ecosystems = args
+        .exclude_ecosystem
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    // Get existing dependabot file
+    let existing_dependabot = get_dependabot_yml(
+        forge,
+        repo,
+        default_branch(repo),
+        args.dependabot_filename.as_deref(),
+    )
+    .await?;
+
+    if existing_dependabot.is_none() && !args.force_new {
+        log::info!(
+            "No existing dependabot config for repo {}, not creating a PR without --force-new",
+            repo.name
+        );
+        return Ok(RepoReport::skipped(
+            &repo.name,
+            repo_level,
+            "no existing dependabot config",
+        ));
+    }
+
+    if let Some((_, existing_content)) = &existing_dependabot
+        && !args.takeover
+        && !is_generated(existing_content, &dependabot_overrides.defaults)
+    {
+        log::warn!(
+            "Skipping repo {} as its dependabot config was not generated by this tool, pass --takeover to replace it",
+            repo.name
+        );
+        return Ok(RepoReport::skipped(
+            &repo.name,
+            repo_level,
+            "config not generated by this tool",
+        ));
+    }
+
+    if args.only_existing {
+        let prs = forge.open_prs(&repo.name, args.bot_branch(), None).await?;
+        if prs.is_empty() {
+            log::info!("Skipping repo {} as it has no open PR", repo.name);
+            return Ok(RepoReport::skipped(&repo.name, repo_level, "no open PR"));
+        }
+    }
+
+    // Excluded github-actions don't need the workflow directories to be fetched.
+    let gha_directories = if policy.is_excluded("github-actions") {
+        vec![]
+    } else {
+        find_gha_directories(forge, repo, &dependabot_overrides.defaults).await?
+    };
+    let manifests = ecosystems
+        .get(&repo.full_name)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let inputs = state_inputs(
+        args,
+        dependabot_overrides,
+        level,
+        manifests,
+        &gha_directories,
+    )?;
+    if !args.force_new
+        && state.contains(&repo.name)
+        && let Some((_, file)) = &existing_dependabot
+        && let Some(content) = &file.content
+        && state.is_up_to_date(&repo.name, content, &inputs)
+    {
+        log::info!("Skipping repo {} as its config is up to date", repo.name);
+        return Ok(RepoReport::skipped(
+            &repo.name,
+            repo_level,
+            "config up to date",
+        ));
+    }
+
+    let config = build_config(
+        &repo.name,
+        default_branch(repo),
+        gha_directories,
+        manifests,
+        dependabot_overrides,
+        &policy,
+        args.collapse_directories,
+    )
+    .with_context(|| format!("generated config for {} is invalid", repo.name))?;
+
+    let detected_ecosystems = config
+        .updates
+        .iter()
+        .map(|update| update.package_ecosystem.clone())
+        .collect::<Vec<_>>();
+    let mut schedules = config
+        .updates
+        .iter()
+        .map(|update| update.schedule.slot())
+        .collect::<Vec<_>>();
+    schedules.sort();
+    schedules.dedup();
+
+    let mut reason = None;
+    let status = if !config.updates.is_empty() {
+        if args.verbose || args.print_only {
+            let content = config.to_annotated_yaml()?;
+
+            println!("{}", content);
+        }
+
+        config
+            .validate()
+            .with_context(|| format!("generated config for {} is invalid", repo.name))?;
+
+        if args.self_check || cfg!(debug_assertions) {
+            config
+                .check_round_trip()
+                .with_context(|| format!("generated config for {} is invalid", repo.name))?;
+        }
+
+        if args.schema_validate {
+            schema::validate_schema(&config)
+                .with_context(|| format!("generated config for {} is invalid", repo.name))?;
+        }
+
+        let status = if let Some(output_dir) = &args.output_dir {
+            write_config(
+                output_dir,
+                repo,
+                &config,
+                &dependabot_overrides.defaults,
+                args.dependabot_filename.as_deref(),
+            )?;
+            RepoStatus::Written
+        } else if args.print_only {
+            RepoStatus::Printed
+        } else if args.check {
+            let existing_content = existing_dependabot
+                .as_ref()
+                .and_then(|(_, file)| file.content.clone());
+            if existing_content == Some(render_config(&config, &dependabot_overrides.defaults)?) {
+                RepoStatus::Unchanged
+            } else {
+                RepoStatus::Drifted
+            }
+        } else if args.only_changed_ecosystems
+            && existing_dependabot.as_ref().is_some_and(|(existing, _)| {
+                existing.ecosystem_directories() == config.ecosystem_directories()
+            })
+        {
+            log::info!(
+                "Skipping repo {} as its ecosystems and directories did not change",
+                repo.name
+            );
+            reason = Some("ecosystems unchanged");
+            RepoStatus::Skipped
+        } else if writes.prompt.aborted() {
+            reason = Some("run aborted");
+            RepoStatus::Skipped
+        } else if !writes.limit.try_reserve() {
+            reason = Some("--limit reached");
+            RepoStatus::Skipped
+        } else {
+            // Only repos without an open bot PR count against --max-open-prs.
+            let new_pr = args.max_open_prs.is_some()
+                && forge
+                    .open_prs(&repo.name, args.bot_branch(), None)
+                    .await?
+                    .is_empty();
+            if new_pr && !writes.new_prs.try_reserve() {
+                log::info!(
+                    "Deferring the PR for {} as --max-open-prs bot PRs are open",
+                    repo.name
+                );
+                writes.limit.finish(RepoStatus::Skipped);
+                reason = Some("--max-open-prs reached");
+                RepoStatus::Skipped
+            } else {
+                let _permit = writes.permits.acquire().await?;
+                let status = create_pr(
+                    forge,
+                    repo,
+                    args,
+                    &config,
+                    &dependabot_overrides.defaults,
+                    writes,
+                )
+                .await;
+                let status = match skip_rejected_archived(repo, status)? {
+                    Some(status) => status,
+                    None => {
+                        reason = Some("archived");
+                        RepoStatus::Skipped
+                    }
+                };
+                writes.limit.finish(status);
+                if new_pr {
+                    writes.new_prs.finish(status);
+                }
+                if status == RepoStatus::Skipped && reason.is_none() {
+                    reason = Some(if writes.prompt.aborted() {
+                        "run aborted"
+                    } else {
+                        "declined interactively"
+                    });
+                }
+                status
+            }
+        };
+
+        if matches!(
+            status,
+            RepoStatus::Created | RepoStatus::Updated | RepoStatus::Unchanged
+        ) {
+            state.record(
+                &repo.name,
+                &render_config(&config, &dependabot_overrides.defaults)?,
+                &inputs,
+            );
+        }
+
+        status
+    } else {
+        log::warn!("No potential dependabot config found for {}", repo.name);
+        if args.check && existing_dependabot.is_some() {
+            RepoStatus::Drifted
+        } else if args.remove_orphaned
+            && !args.print_only
+            && args.output_dir.is_none()
+            && existing_dependabot.is_some()
+        {
+            if writes.limit.try_reserve() {
+                let _permit = writes.permits.acquire().await?;
+                let status = remove_config_pr(
+                    forge,
+                    repo,
+                    args,
+                    &dependabot_overrides.defaults,
+                    &writes.pr_throttle,
+                )
+                .await;
+                let status = match skip_rejected_archived(repo, status)? {
+                    Some(status) => status,
+                    None => {
+                        reason = Some("archived");
+                        RepoStatus::Skipped
+                    }
+                };
+                writes.limit.finish(status);
+                status
+            } else {
+                reason = Some("--limit reached");
+                RepoStatus::Skipped
+            }
+        } else {
+            RepoStatus::NoConfig
+        }
+    };
+
+    Ok(RepoReport {
+        repo: &repo.name,
+        level: repo_level,
+        status,
+        reason,
+        ecosystems: detected_ecosystems,
+        schedules,
+    })
+}
+
+/// Everything the generated config of a repo depends on, so that the state file notices when any
+/// of them changes: the overrides, the asset level, the detected manifests and workflow
+/// directories, and the flags that change the generated file.
+fn state_inputs(
+    args: &Args,
+    overrides: &DependabotOverrides,
+    level: AssetLevel,
+    manifests: &[(String, Ecosystem)],
+    gha_directories: &[(String, String)],
+) -> anyhow::Result<String> {
+    // Tool specific override attributes are not serialized with the overrides.
+    let override_flags = overrides
+        .updates
+        .iter()
+        .map(|(repo, updates)| {
+            let flags = updates
+                .iter()
+                .map(|update| {
+                    (
+                        &update.package_ecosystem,
+                        update.groups_override,
+                        update.cooldown_additive,
+                    )
+                })
+                .collect::<Vec<_>>();
+            (repo, flags)
+        })
+        .collect::<IndexMap<_, _>>();
+
+    Ok(serde_json::to_string(&serde_json::json!({
+        "overrides": overrides,
+        "override-flags": override_flags,
+        "level": level,
+        "manifests": manifests,
+        "gha-directories": gha_directories,
+        "no-cooldown": args.no_cooldown,
+        "enable-beta-ecosystems": args.enable_beta_ecosystems,
+        "exclude-ecosystem": args.exclude_ecosystem,
+        "collapse-directories": args.collapse_directories,
+        "dependabot-filename": args.dependabot_filename,
+        "level-property": args.level_property,
+    }))?)
+}
+
+/// Builds the dependabot config of a repo from its detected github-actions directories and
+/// manifests, the overrides and the policy of its asset level. Does no I/O, so it can be tested
+/// without GitHub.
+fn build_config(
+    repo: &str,
+    default_branch: &str,
+    gha_directories: Vec<(String, String)>,
+    manifests: &[(String, Ecosystem)],
+    overrides: &DependabotOverrides,
+    policy: &Policy,
+    collapse_threshold: usize,
+) -> anyhow::Result<DependabotConfig> {
+    let mut updates = gha_directories
+        .into_iter()
+        .map(|(directory, detected_from)| {
+            let gha_update = Update {
+                package_ecosystem: "github-actions".to_string(),
+                directory: Some(directory),
+                schedule: policy.schedule.clone(),
+                open_pull_requests_limit: policy.open_pull_requests_limit("github-actions"),
+                commit_message: policy.commit_message("github-actions"),
+                versioning_strategy: policy.versioning_strategy("github-actions"),
+                ignore: policy.ignore("github-actions"),
+                allow: policy.allow("github-actions"),
+                insecure_external_code_execution: policy
+                    .insecure_external_code_execution("github-actions"),
+                reviewers: policy.reviewers.clone(),
+                labels: policy.labels.clone(),
+                groups: Some(policy.groups.clone()),
+                cooldown: policy.cooldown("github-actions"),
+                registries: policy.registry_names("github-actions"),
+                detected_from: vec![detected_from],
+                ..Update::default()
+            };
+            apply_override(
+                gha_update,
+                &overrides.updates,
+                repo,
+                &Ecosystem::GitHubActions.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (manifest, ecosystem) in manifests {
+        if policy.is_excluded(&ecosystem.to_string()) {
+            log::debug!(
+                "Skipping excluded ecosystem {} of repo {} detected from {}",
+                ecosystem,
+                repo,
+                manifest_path(manifest)
+            );
             continue;
         }
-
-        let props = octocrab
-            .list_custom_properties("KittyCAD", &repo.name)
-            .await?;
-
-        let repo_level = AssetLevel::get_from_props(&props);
-
-        if repo_level.is_none() || repo_level == Some(AssetLevel::Playground) {
-            log::debug!("Skipping repo {} as it is a playground repo", repo.name);
+        if ecosystem.is_beta() && !policy.enable_beta_ecosystems {
+            log::debug!(
+                "Skipping beta ecosystem {} of repo {} detected from {}, pass --enable-beta-ecosystems to generate it",
+                ecosystem,
+                repo,
+                manifest_path(manifest)
+            );
             continue;
         }
 
-        // Get existing dependabot file
-        let existing_dependabot = get_dependabot_yml(&octocrab, repo, "main").await?;
+        // The name of the Helm ecosystem is configurable, all others are fixed.
+        let package_ecosystem = if *ecosystem == Ecosystem::Helm {
+            policy.helm_ecosystem.clone()
+        } else {
+            ecosystem.to_string()
+        };
 
-        if existing_dependabot.is_none() && !args.force_new {
-            println!(
-                "No existing dependabot config for repo {}, not creating a PR without --force-new",
-                repo.name
+        // Dependabot reads .gitmodules from the root only, wherever the detection found one.
+        let path = if *ecosystem == Ecosystem::Submodule {
+            "/".to_string()
+        } else {
+            manifest_directory(manifest)
+        };
+
+        // Several manifests of an ecosystem in one directory, e.g. requirements.txt and
+        // pyproject.toml, share a single update, as Dependabot rejects duplicate update blocks.
+        if let Some(update) = updates.iter_mut().find(|update| {
+            update.package_ecosystem == package_ecosystem
+                && (update.directory.as_ref() == Some(&path)
+                    || update
+                        .directories
+                        .as_ref()
+                        .is_some_and(|directories| directories.contains(&path)))
+        }) {
+            log::debug!(
+                "Merging {} into the {} update of repo {} in {}",
+                manifest_path(manifest),
+                ecosystem,
+                repo,
+                path
             );
+            // TODO: If we configure target-branch, then we have to take this into consideration here aswell
+            update.detected_from.push(manifest_path(manifest));
             continue;
         }
 
-        if args.only_existing {
-            let prs = octocrab
-                .pulls("KittyCAD", &repo.name)
-                .list()
-                .state(State::Open)
-                .base("main")
-                .head("KittyCAD:ciso/update-dependabot")
-                .send()
-                .await?
-                .items;
-            if prs.is_empty() {
-                log::info!("Skipping repo {} as it has no open PR", repo.name);
-                continue;
-            }
-        }
-
-        // Find updates
-        let has_gha_config = has_gha_config(&octocrab, repo).await?;
-
-        let mut updates = if has_gha_config {
-            let gha_update = Update {
-                package_ecosystem: "github-actions".to_string(),
-                directory: Some("/".to_string()),
-                schedule: default_schedule.clone(),
-                open_pull_requests_limit,
-                groups: Some(default_groups.clone()),
-                cooldown: Some(default_cooldown.clone()),
-                ..Update::default()
-            };
-            vec![apply_override(
-                gha_update,
-                &dependabot_overrides.updates,
-                repo,
-                &Ecosystem::GitHubActions,
-            )]
+        // Glob members of virtual workspaces are only supported by `directories`.
+        let (directory, directories) = if path.contains('*') {
+            (None, Some(vec![path]))
         } else {
-            vec![]
+            (Some(path), None)
         };
 
-        if let Some(ecosystems) =
-            ecosystems.get(repo.full_name.as_ref().expect("full name must exist"))
-        {
-            for (path, ecosystem) in ecosystems {
-                // Remove /repositories/848456627/contents/
-                let path = path.split("/").skip(4).collect::<Vec<_>>();
-                // Remove last filename
-                let path = "/".to_string() + &path[..path.len() - 1].join("/");
-
-                if updates.iter().any(|update| {
-                    update.directory.as_ref() == Some(&path)
-                        && update.package_ecosystem == ecosystem.to_string()
-                }) {
-                    log::warn!(
-                        "Tried to generate an update config that would conflict with existing one for repo {} and ecosystem {} in {}. Skipping...",
-                        repo.name,
-                        ecosystem,
-                        path
-                    );
-                    // TODO: If we configure target-branch, then we have to take this into consideration here aswell
-                    continue;
-                }
+        let update = Update {
+            package_ecosystem: package_ecosystem.clone(),
+            directory,
+            directories,
+            schedule: policy.schedule.clone(),
+            groups: Some(policy.groups.clone()),
+            open_pull_requests_limit: policy.open_pull_requests_limit(&package_ecosystem),
+            commit_message: policy.commit_message(&package_ecosystem),
+            versioning_strategy: policy.versioning_strategy(&package_ecosystem),
+            ignore: policy.ignore(&package_ecosystem),
+            allow: policy.allow(&package_ecosystem),
+            insecure_external_code_execution: policy
+                .insecure_external_code_execution(&package_ecosystem),
+            reviewers: policy.reviewers.clone(),
+            labels: policy.labels.clone(),
+            cooldown: policy.cooldown(&package_ecosystem),
+            registries: policy.registry_names(&package_ecosystem),
+            detected_from: vec![manifest_path(manifest)],
+            ..Update::default()
+        };
 
-                let cooldown = match ecosystem {
-                    Ecosystem::Submodule => None,
-                    _ => Some(default_cooldown.clone()),
-                };
+        // Apply overrides
+        let update = apply_override(update, &overrides.updates, repo, &package_ecosystem);
 
-                let update = Update {
-                    package_ecosystem: ecosystem.to_string(),
-                    directory: Some(path),
-                    schedule: default_schedule.clone(),
-                    groups: Some(default_groups.clone()),
-                    reviewers: None,
-                    open_pull_requests_limit,
-                    cooldown,
-                    ..Update::default()
-                };
+        updates.push(update);
 
-                // Apply overrides
-                let update = apply_override(update, &dependabot_overrides.updates, repo, ecosystem);
+        log::debug!("Found ecosystem {:?} in repo {}", ecosystem, repo);
+    }
 
-                updates.push(update);
+    if policy.no_cooldown {
+        for update in &mut updates {
+            update.cooldown = None;
+        }
+    }
 
-                log::debug!("Found ecosystem {:?} in repo {}", ecosystem, repo.name);
-            }
+    let mut updates = collapse_directories(updates, collapse_threshold);
+    // Sort the updates so consecutive runs produce the same YAML regardless of detection order.
+    updates.sort_by(|a, b| {
+        a.package_ecosystem
+            .cmp(&b.package_ecosystem)
+            .then_with(|| a.directory.cmp(&b.directory))
+            .then_with(|| a.directories.cmp(&b.directories))
+    });
+    let updates = match overrides.target_branches.get(repo) {
+        Some(branches) if !branches.is_empty() => {
+            target_branch_updates(updates, branches, default_branch)
         }
+        _ => updates,
+    };
 
-        // We don't generate registries right now so we can just take the overrides if they exist for the repo.
-        let repo_registries = dependabot_overrides.registries.get(&repo.name);
-        let registries = if let Some(repo_registries) = repo_registries
-            && !dependabot_overrides.registries.is_empty()
-        {
-            Some(repo_registries.clone())
-        } else {
-            None
-        };
+    // Generate the registries referenced by the updates, registries from the overrides win.
+    let mut registries = updates
+        .iter()
+        .flat_map(|update| update.registries.iter().flatten())
+        .filter_map(|name| {
+            policy
+                .registries
+                .get(name)
+                .map(|template| (name.clone(), template.registry.clone()))
+        })
+        .collect::<Registries>();
+    if let Some(repo_registries) = overrides.registries.get(repo) {
+        registries.extend(repo_registries.clone());
+    }
+    let registries = if registries.is_empty() {
+        None
+    } else {
+        Some(registries)
+    };
 
-        // Apply updates if necessary
-        if !updates.is_empty() {
-            let config = DependabotConfig {
-                version: 2,
-                updates,
-                registries,
-            };
+    let beta = manifests
+        .iter()
+        .any(|(_, ecosystem)| ecosystem.is_beta() && policy.enable_beta_ecosystems);
+    let mut config = DependabotConfig {
+        version: 2,
+        enable_beta_ecosystems: beta.then_some(true),
+        updates,
+        registries,
+    };
+    config.normalize_cooldowns()?;
 
-            if args.verbose {
-                let content = serde_yaml_ng::to_string(&config)?;
+    Ok(config)
+}
 
-                println!("{}", content);
-            }
+/// Copies the updates for every branch in `branches`, setting their `target-branch`. Dependabot
+/// only reads the config of the default branch, so release lines need their own update blocks
+/// there instead of configs on their branches. The updates for the default branch are always kept
+/// without a `target-branch`, whether it is listed or not. Updates with a `target-branch` override
+/// are kept once as they are.
+fn target_branch_updates(
+    updates: Vec<Update>,
+    branches: &[String],
+    default_branch: &str,
+) -> Vec<Update> {
+    let (fixed, updates): (Vec<_>, Vec<_>) = updates
+        .into_iter()
+        .partition(|update| update.target_branch.is_some());
 
-            create_pr(&octocrab, repo, &config, !args.create_pr).await?;
-        } else {
-            log::warn!("No potential dependabot config found for {}", repo.name);
-            // TODO: Potentially make a PR to remove the file?
+    let mut release_branches: Vec<&String> = vec![];
+    for branch in branches {
+        if branch != default_branch && !release_branches.contains(&branch) {
+            release_branches.push(branch);
         }
     }
-    Ok(())
+
+    let mut expanded = updates.clone();
+    for branch in release_branches {
+        for update in &updates {
+            let mut update = update.clone();
+            update.target_branch = Some(branch.clone());
+            expanded.push(update);
+        }
+    }
+    expanded.extend(fixed);
+    expanded
+}
+
+/// Collapses the update blocks of an ecosystem into a single block using `directories` once there
+/// are more than `threshold` of them, e.g. for monorepos with many packages.
+///
+/// The blocks only differ in their directory, as they are generated from the same policy and
+/// overrides.
+fn collapse_directories(updates: Vec<Update>, threshold: usize) -> Vec<Update> {
+    let mut by_ecosystem: IndexMap<String, Vec<Update>> = IndexMap::new();
+    for update in updates {
+        by_ecosystem
+            .entry(update.package_ecosystem.clone())
+            .or_default()
+            .push(update);
+    }
+
+    by_ecosystem
+        .into_values()
+        .flat_map(|updates| {
+            if updates.len() <= threshold
+                || updates
+                    .iter()
+                    .any(|update| update.directory.is_none() || update.directories.is_some())
+            {
+                return updates;
+            }
+
+            let directories = updates
+                .iter()
+                .filter_map(|update| update.directory.clone())
+                .collect::<Vec<_>>();
+            let detected_from = updates
+                .iter()
+                .flat_map(|update| update.detected_from.clone())
+                .collect::<Vec<_>>();
+            let mut update = updates
+                .into_iter()
+                .next()
+                .expect("more than threshold updates");
+            update.directory = None;
+            update.directories = Some(directories_glob(directories));
+            update.detected_from = detected_from;
+            vec![update]
+        })
+        .collect()
+}
+
+/// Uses a single `<prefix>/*` glob if all directories are direct children of their common prefix
+/// and otherwise lists the directories.
+fn directories_glob(directories: Vec<String>) -> Vec<String> {
+    let segments = directories
+        .iter()
+        .map(|directory| {
+            directory
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let prefix_len = (0..)
+        .take_while(|i| {
+            segments
+                .iter()
+                .all(|segment| segment.len() > *i && segment[*i] == segments[0][*i])
+        })
+        .count();
+
+    if segments
+        .iter()
+        .all(|segment| segment.len() == prefix_len + 1)
+    {
+        let prefix = segments[0][..prefix_len]
+            .iter()
+            .map(|segment| format!("/{segment}"))
+            .collect::<String>();
+        vec![format!("{prefix}/*")]
+    } else {
+        directories
+    }
 }
 
 fn apply_override(
     update: Update,
     dependabot_overrides: &IndexMap<String, Vec<UpdateOverride>>,
-    repo: &Repository,
-    ecosystem: &Ecosystem,
+    repo: &str,
+    package_ecosystem: &str,
 ) -> Update {
-    if let Some(override_updates) = dependabot_overrides.get(&repo.name) {
+    if let Some(override_updates) = dependabot_overrides.get(repo) {
+        // Repo-level overrides first, so that overrides for the ecosystem win. There is at most
+        // one of each, see `DependabotOverrides::validate_updates`.
+        let repo_overrides = override_updates
+            .iter()
+            .filter(|update| update.package_ecosystem.is_none())
+            .collect::<Vec<_>>();
         let matching_overrides = override_updates
             .iter()
-            .filter(|update| update.package_ecosystem == ecosystem.to_string())
+            .filter(|update| update.package_ecosystem.as_deref() == Some(package_ecosystem))
             .collect::<Vec<_>>();
 
-        if matching_overrides.len() > 1 {
-            panic!("found more than one override");
-        }
-
-        log::debug!("found override for repo {}", repo.name);
+        log::debug!("found override for repo {}", repo);
 
-        if let Some(override_update) = matching_overrides.first() {
-            update.override_config(override_update)
-        } else {
-            update
-        }
+        repo_overrides
+            .into_iter()
+            .chain(matching_overrides)
+            .fold(update, |update, override_update| {
+                update.override_config(override_update)
+            })
     } else {
         update
     }
 }
 
-async fn create_pr(
-    octocrab: &Octocrab,
-    repo: &Repository,
+/// Renders the config as it is committed to the repo, starting with the header of
+/// [`PolicyDefaults::header`].
+fn render_config(config: &DependabotConfig, defaults: &PolicyDefaults) -> anyhow::Result<String> {
+    Ok(defaults.header() + &config.to_annotated_yaml()?)
+}
+
+/// Writes the config to `<output_dir>/<repo>/.github/dependabot.yml`, or the --dependabot-filename,
+/// mirroring what would be committed.
+fn write_config(
+    output_dir: &str,
+    repo: &Repo,
     config: &DependabotConfig,
-    dry: bool,
+    defaults: &PolicyDefaults,
+    dependabot_filename: Option<&str>,
 ) -> anyhow::Result<()> {
-    let octocrab_repo = octocrab.repos("KittyCAD", &repo.name);
+    let path = std::path::Path::new(output_dir)
+        .join(&repo.name)
+        .join(dependabot_paths(dependabot_filename)[0]);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, render_config(config, defaults)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    log::info!(
+        "Wrote dependabot config of {} to {}",
+        repo.name,
+        path.display()
+    );
 
-    let main_ref = octocrab_repo
-        .get_ref(&Reference::Branch("main".to_string()))
-        .await
-        .context("failed to fetch ref to main branch")?;
+    Ok(())
+}
 
-    // FIXME: With closed PRs it wont reopen and update the branch, so we need to check for existing PRs and update those branches instead.
-    let existing_config = if octocrab_repo
-        .get_ref(&Reference::Branch("ciso/update-dependabot".to_string()))
+/// Whether the config carries the header of generated configs, i.e. it is not a hand-written
+/// config.
+fn is_generated(file: &RemoteFile, defaults: &PolicyDefaults) -> bool {
+    file.content
+        .as_ref()
+        .is_some_and(|content| content.starts_with(&defaults.header()))
+}
+
+/// Opens a PR deleting the dependabot config of a repo without any detected ecosystems. Only
+/// generated configs are removed.
+async fn remove_config_pr(
+    forge: &impl Forge,
+    repo: &Repo,
+    args: &Args,
+    defaults: &PolicyDefaults,
+    pr_throttle: &PrThrottle,
+) -> anyhow::Result<RepoStatus> {
+    let branch = args.bot_branch();
+    let dependabot_filename = args.dependabot_filename.as_deref();
+    let Some(existing_content) =
+        get_dependabot_yml_content(forge, repo, default_branch(repo), dependabot_filename).await?
+    else {
+        return Ok(RepoStatus::NoConfig);
+    };
+
+    if !is_generated(&existing_content, defaults) {
+        log::warn!(
+            "Not removing dependabot config of {} as it was not generated by this tool",
+            repo.name
+        );
+        return Ok(RepoStatus::NoConfig);
+    }
+
+    if !args.create_pr {
+        log::info!(
+            "Would remove dependabot config of {}. Pass --create-pr to perform the changes.",
+            repo.name
+        );
+        return Ok(RepoStatus::WouldRemove);
+    }
+
+    forge
+        .create_branch(&repo.name, branch, default_branch(repo))
+        .await?;
+
+    let Some(branch_content) =
+        get_dependabot_yml_content(forge, repo, branch, dependabot_filename).await?
+    else {
+        log::info!("Dependabot config of {} is already removed", repo.name);
+        return Ok(RepoStatus::Unchanged);
+    };
+
+    log::info!("Removing dependabot file for {}", repo.name);
+    forge
+        .delete_file(
+            &repo.name,
+            branch,
+            &branch_content,
+            &format!("Remove dependabot config from {}", defaults.source_repo()),
+        )
+        .await?;
+
+    let slot = pr_throttle.wait().await;
+    match forge
+        .open_pr(
+            &repo.name,
+            branch,
+            default_branch(repo),
+            "Remove dependabot config",
+            &defaults.fill_source_placeholders(
+                "This PR was automatically generated from {source_repo}. No package ecosystems were detected in this repository anymore, so the dependabot config is removed.",
+            ),
+            args.draft,
+        )
         .await
-        .is_err()
+        .context("failed to create PR")?
     {
-        // Create branch
-        if !dry {
-            octocrab_repo
-                .create_ref(
-                    &Reference::Branch("ciso/update-dependabot".to_string()),
-                    match main_ref.object {
-                        Object::Commit { sha, .. } => sha,
-                        Object::Tag { sha, .. } => sha,
-                        _ => panic!("unexpected object type"),
-                    },
-                )
-                .await?;
+        OpenedPr::Created(pr) => {
+            slot.opened();
+            log::info!(
+                "Created PR for {}: {}",
+                repo.name,
+                pr.url.unwrap_or("no url".to_string())
+            )
+        }
+        OpenedPr::AlreadyExists => {
+            log::info!("PR for {} already exists", repo.name)
+        }
+    }
+
+    Ok(RepoStatus::Removed)
+}
+
+/// GitHub rejects every write to an archived repo, which are only processed with
+/// --include-archived. Their failed writes are logged and the repo is skipped (`None`) instead of
+/// failing the run.
+fn skip_rejected_archived(
+    repo: &Repo,
+    status: anyhow::Result<RepoStatus>,
+) -> anyhow::Result<Option<RepoStatus>> {
+    match status {
+        Err(e) if repo.archived => {
+            log::warn!(
+                "Skipping archived repo {} as GitHub rejected the changes: {:#}",
+                repo.name,
+                e
+            );
+            Ok(None)
+        }
+        status => status.map(Some),
+    }
+}
+
+/// Closes the open bot PR and deletes the bot branch of an archived repo, as they can never be
+/// merged. GitHub only allows this after the repo is unarchived, until then the errors are
+/// logged on every run.
+async fn cleanup_archived(
+    forge: &impl Forge,
+    repo: &Repo,
+    branch: &str,
+    dry: bool,
+) -> anyhow::Result<()> {
+    let open_prs = forge.open_prs(&repo.name, branch, None).await?;
+    if dry {
+        for pr in &open_prs {
+            log::info!(
+                "Would close PR #{} of archived repo {}. Pass --create-pr to perform the changes.",
+                pr.number,
+                repo.name
+            );
         }
+        log::info!(
+            "Would delete branch {} of archived repo {} if it exists",
+            branch,
+            repo.name
+        );
+        return Ok(());
+    }
+
+    for pr in open_prs {
+        forge
+            .close_pr(&repo.name, pr.number)
+            .await
+            .with_context(|| format!("failed to close PR #{}", pr.number))?;
+        log::info!("Closed PR #{} of archived repo {}", pr.number, repo.name);
+    }
+
+    if forge
+        .delete_branch(&repo.name, branch)
+        .await
+        .with_context(|| format!("failed to delete branch {branch}"))?
+    {
+        log::info!("Deleted branch {} of archived repo {}", branch, repo.name);
+    }
+
+    Ok(())
+}
+
+/// The ecosystems named in the PR title and body, each once in the order of their first update.
+fn pr_ecosystems(config: &DependabotConfig) -> Vec<String> {
+    config
+        .updates
+        .iter()
+        .map(|update| update.package_ecosystem.clone())
+        .collect::<IndexSet<_>>()
+        .into_iter()
+        .collect()
+}
 
-        // get current config from main
-        get_dependabot_yml_content(octocrab, repo, "main").await?
+/// Commits the config to the bot branch and opens a PR for it.
+///
+/// A dry run never touches the bot branch, not even for reading. It always compares against the
+/// config on the default branch, so its output shows what a fresh run would do regardless of earlier runs.
+///
+/// With --interactive a declined change is reported as skipped. The bot branch may already have
+/// been created at that point, but it only matches main then.
+async fn create_pr(
+    forge: &impl Forge,
+    repo: &Repo,
+    args: &Args,
+    config: &DependabotConfig,
+    defaults: &PolicyDefaults,
+    writes: &Writes,
+) -> anyhow::Result<RepoStatus> {
+    let branch = args.bot_branch();
+    let base = default_branch(repo);
+    let dry = !args.create_pr;
+    // FIXME: With closed PRs it wont reopen and update the branch, so we need to check for existing PRs and update those branches instead.
+    // A dry run never creates the branch, a freshly created branch is the same as the base.
+    let existing_config = if dry || forge.create_branch(&repo.name, branch, base).await? {
+        // get current config from the default branch
+        get_dependabot_yml_content(forge, repo, base, args.dependabot_filename.as_deref()).await?
     } else {
         // get current config from branch
-        get_dependabot_yml_content(octocrab, repo, "ciso/update-dependabot").await?
+        get_dependabot_yml_content(forge, repo, branch, args.dependabot_filename.as_deref()).await?
     };
 
-    let content = serde_yaml_ng::to_string(&config)?;
-    let content = "# DO NOT EDIT THIS FILE. This dependabot file was generated \n\
-                # by https://github.com/KittyCAD/ciso Changes to this file should be addressed in \n\
-                # the ciso repository.\n\n".to_string() + &content;
+    let content = render_config(config, defaults)?;
 
-    if let Some(existing_content) = existing_config {
-        if let Some(decoded_content) = existing_content.decoded_content()
-            && decoded_content == content
+    let ecosystems = pr_ecosystems(config);
+    let title = defaults.pr_title(&repo.name, &ecosystems);
+    let body = defaults.pr_body(&repo.name, &ecosystems);
+
+    let status = match (dry, existing_config.is_some()) {
+        (true, true) => RepoStatus::WouldUpdate,
+        (true, false) => RepoStatus::WouldCreate,
+        (false, true) => RepoStatus::Updated,
+        (false, false) => RepoStatus::Created,
+    };
+
+    if let Some(existing_content) = &existing_config
+        && existing_content.content.as_ref() == Some(&content)
+    {
+        log::info!(
+            "No changes on {} for {}",
+            if dry { base } else { branch },
+            repo.name
+        );
+        if !dry {
+            retarget_prs(forge, repo, branch, base).await?;
+            update_existing_pr(forge, repo, branch, base, &title, &body).await?;
+        }
+        return Ok(RepoStatus::Unchanged);
+    }
+
+    if !dry {
+        let existing_content = existing_config
+            .as_ref()
+            .and_then(|file| file.content.as_deref());
+        match writes
+            .prompt
+            .confirm(&repo.name, existing_content, &content)
+            .await?
         {
-            log::info!("No changes on ciso/update-dependabot for {}", repo.name);
-            return Ok(());
+            Decision::Proceed => {}
+            Decision::Skip | Decision::Abort => {
+                log::info!("Not changing the dependabot config of {}", repo.name);
+                return Ok(RepoStatus::Skipped);
+            }
         }
+    }
 
+    if let Some(existing_content) = existing_config {
         if !dry {
             log::info!("Updating dependabot file for {}", repo.name);
-            octocrab_repo
+            // Write back to whichever extension is already used
+            forge
                 .update_file(
-                    ".github/dependabot.yml",
-                    "Update dependabot config from KittyCAD/ciso",
+                    &repo.name,
+                    branch,
+                    &existing_content,
+                    &format!("Update dependabot config from {}", defaults.source_repo()),
                     &content,
-                    existing_content.sha,
                 )
-                .branch("ciso/update-dependabot")
-                .send()
                 .await?;
         }
     } else if !dry {
         log::info!("Creating dependabot file for {}", repo.name);
-        octocrab_repo
+        forge
             .create_file(
-                ".github/dependabot.yml",
-                "Update dependabot config from KittyCAD/ciso",
+                &repo.name,
+                branch,
+                dependabot_paths(args.dependabot_filename.as_deref())[0],
+                &format!("Update dependabot config from {}", defaults.source_repo()),
                 &content,
             )
-            .branch("ciso/update-dependabot")
-            .send()
             .await?;
     }
 
     if !dry {
-        match octocrab
-            .pulls("KittyCAD", &repo.name)
-            .create("Update dependabot config", "ciso/update-dependabot", "main")
-            .body("This PR was automatically generated from KittyCAD/ciso. Let @maxammann know if you want changes applied to the PR. Please merge this soon.")
-            .send()
-            .await {
-            Ok(r) => {
-                log::info!("Created PR for {}: {}", repo.name, r.html_url.map(|url| url.to_string()).unwrap_or("no url".to_string()));
-
-                // TODO octocrab.pulls("KittyCAD", &repo.name).request_reviews(r.number, vec!["maxammann".to_string()], vec![]).await?;
+        retarget_prs(forge, repo, branch, base).await?;
+        let slot = writes.pr_throttle.wait().await;
+        match forge
+            .open_pr(&repo.name, branch, base, &title, &body, args.draft)
+            .await
+            .context("failed to create PR")?
+        {
+            OpenedPr::Created(pr) => {
+                slot.opened();
+                log::info!(
+                    "Created PR for {}: {}",
+                    repo.name,
+                    pr.url.unwrap_or("no url".to_string())
+                );
+                assign_pr(forge, repo, pr.number, &args.assignee).await?;
+                request_team_reviews(forge, repo, pr.number, &args.team_reviewer).await;
+            }
+            OpenedPr::AlreadyExists => {
+                log::info!("PR for {} already exists", repo.name);
+                update_existing_pr(forge, repo, branch, base, &title, &body).await?;
             }
-            Err(e) => log::warn!("Did not create a (new) PR for {}. Likely it already exists. origin: {}", repo.name, e)
         }
-    } else {
-        log::info!(
-            "Would create or update PR for {}. Pass --create-pr to perform the changes.",
-            repo.name
-        );
+    } else {
+        log::info!(
+            "Would create or update PR for {}. Pass --create-pr to perform the changes.",
+            repo.name
+        );
+    }
+
+    Ok(status)
+}
+
+/// Assigns the PR to all `assignees` that can be assigned in the repo, others are skipped with a
+/// warning.
+async fn assign_pr(
+    forge: &impl Forge,
+    repo: &Repo,
+    number: u64,
+    assignees: &[String],
+) -> anyhow::Result<()> {
+    if assignees.is_empty() {
+        return Ok(());
+    }
+
+    let mut valid = vec![];
+    for assignee in assignees {
+        if forge.can_assign(&repo.name, assignee).await? {
+            valid.push(assignee.as_str());
+        } else {
+            log::warn!(
+                "Not assigning {} to the PR for {} as they cannot be assigned in the repo",
+                assignee,
+                repo.name
+            );
+        }
+    }
+
+    if !valid.is_empty() {
+        forge.add_assignees(&repo.name, number, &valid).await?;
+    }
+
+    Ok(())
+}
+
+/// Points open bot PRs at `base`, e.g. after the default branch was renamed from master to main.
+/// PRs into a branch that no longer is the default one can't be merged.
+async fn retarget_prs(
+    forge: &impl Forge,
+    repo: &Repo,
+    branch: &str,
+    base: &str,
+) -> anyhow::Result<()> {
+    let open_prs = forge.open_prs(&repo.name, branch, None).await?;
+
+    for pr in open_prs.iter().filter(|pr| pr.base != base) {
+        log::info!(
+            "Changing the base of PR #{} for {} from {} to {}",
+            pr.number,
+            repo.name,
+            pr.base,
+            base
+        );
+        forge.update_pr_base(&repo.name, pr.number, base).await?;
+    }
+
+    Ok(())
+}
+
+/// Requests reviews of the PR from all `teams` of the org, e.g. `platform` or
+/// `@KittyCAD/platform`. Teams that don't exist or can't be requested are skipped with a warning,
+/// as the PR is open either way.
+async fn request_team_reviews(forge: &impl Forge, repo: &Repo, number: u64, teams: &[String]) {
+    let mut valid = vec![];
+    for team in teams {
+        // Teams are addressed by slug, drop the org of `@org/slug`.
+        let slug = team
+            .trim_start_matches('@')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+        match forge.team_exists(slug).await {
+            Ok(true) => valid.push(slug),
+            Ok(false) => log::warn!(
+                "Not requesting a review from team {} for {} as it does not belong to the org",
+                team,
+                repo.name
+            ),
+            Err(e) => log::warn!(
+                "Not requesting a review from team {} for {}: {:#}",
+                team,
+                repo.name,
+                e
+            ),
+        }
+    }
+
+    if valid.is_empty() {
+        return;
+    }
+
+    if let Err(e) = forge.request_team_reviews(&repo.name, number, &valid).await {
+        log::warn!(
+            "Failed to request reviews from {} for {}: {:#}",
+            valid.join(", "),
+            repo.name,
+            e
+        );
+    }
+}
+
+/// Refreshes title and body of the open bot PR, so that template changes reach existing PRs.
+async fn update_existing_pr(
+    forge: &impl Forge,
+    repo: &Repo,
+    branch: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+) -> anyhow::Result<()> {
+    let open_prs = forge.open_prs(&repo.name, branch, Some(base)).await?;
+
+    for pr in open_prs {
+        if pr.title.as_deref() == Some(title) && pr.body.as_deref() == Some(body) {
+            continue;
+        }
+
+        log::info!(
+            "Updating title and body of PR #{} for {}",
+            pr.number,
+            repo.name
+        );
+        forge.update_pr(&repo.name, pr.number, title, body).await?;
+    }
+
+    Ok(())
+}
+
+async fn get_dependabot_yml(
+    forge: &impl Forge,
+    repository: &Repo,
+    branch: &str,
+    dependabot_filename: Option<&str>,
+) -> anyhow::Result<Option<(DependabotConfig, RemoteFile)>> {
+    let Some(file) =
+        get_dependabot_yml_content(forge, repository, branch, dependabot_filename).await?
+    else {
+        return Ok(None);
+    };
+
+    let text = file.content.as_ref().context("failed to decode content")?;
+
+    let config = serde_yaml_ng::from_str::<DependabotConfig>(text)?;
+    Ok(Some((config, file)))
+}
+
+/// The branch Dependabot reads its config from and the bot PRs merge into. Repos without one are
+/// empty and skipped before this is needed.
+fn default_branch(repo: &Repo) -> &str {
+    repo.default_branch.as_deref().unwrap_or("main")
+}
+
+/// GitHub honors both extensions for the dependabot config. New configs use the first one.
+const DEPENDABOT_PATHS: [&str; 2] = [".github/dependabot.yml", ".github/dependabot.yaml"];
+
+/// The paths the dependabot config is looked up at, only the --dependabot-filename if one is
+/// given. New configs are written to the first one.
+fn dependabot_paths(dependabot_filename: Option<&str>) -> Vec<&str> {
+    match dependabot_filename {
+        Some(filename) => vec![filename],
+        None => DEPENDABOT_PATHS.to_vec(),
+    }
+}
+
+/// Fetches the dependabot config, whichever of [`dependabot_paths`] exists. The path it was found
+/// at is available via [`RemoteFile::path`].
+async fn get_dependabot_yml_content(
+    forge: &impl Forge,
+    repository: &Repo,
+    branch: &str,
+    dependabot_filename: Option<&str>,
+) -> anyhow::Result<Option<RemoteFile>> {
+    for path in dependabot_paths(dependabot_filename) {
+        if let Some(file) = forge.get_file(&repository.name, path, branch).await? {
+            return Ok(Some(file));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds the directories Dependabot needs a github-actions update for, together with what they
+/// were detected from: the root for workflows and every composite action in `.github/actions/*`.
+/// Repos with only generated workflows get none, see [`PolicyDefaults::generated_workflow_repos`].
+async fn find_gha_directories(
+    forge: &impl Forge,
+    repository: &Repo,
+    defaults: &PolicyDefaults,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut directories = vec![];
+
+    if any_glob_match(&defaults.generated_workflow_repos, &repository.name) {
+        log::debug!(
+            "Skipping github-actions for repo {} as its workflows are generated",
+            repository.name
+        );
+        return Ok(directories);
+    }
+
+    let gha_workflows = count_gha_workflows(
+        forge,
+        repository,
+        defaults.generated_workflow_marker.as_deref(),
+    )
+    .await?;
+    log::debug!(
+        "Found {} workflows in repo {}",
+        gha_workflows,
+        repository.name
+    );
+    if gha_workflows > 0 {
+        directories.push(("/".to_string(), ".github/workflows".to_string()));
+    }
+
+    let actions = forge
+        .list_directory(
+            &repository.name,
+            ".github/actions",
+            default_branch(repository),
+        )
+        .await?;
+    for action in actions.iter().filter(|entry| entry.is_dir) {
+        let manifest = forge
+            .list_directory(&repository.name, &action.path, default_branch(repository))
+            .await?
+            .into_iter()
+            .find(|entry| entry.name == "action.yml" || entry.name == "action.yaml");
+
+        if let Some(manifest) = manifest {
+            directories.push((format!("/{}", action.path), manifest.path));
+        }
     }
 
-    Ok(())
+    Ok(directories)
 }
 
-async fn get_dependabot_yml(
-    octocrab: &Octocrab,
-    repository: &Repository,
-    branch: &str,
-) -> anyhow::Result<Option<(DependabotConfig, String)>> {
-    let Some(content) = get_dependabot_yml_content(octocrab, repository, branch).await? else {
-        return Ok(None);
+/// Counts the workflow files in `.github/workflows`. Other files like READMEs or disabled
+/// workflows don't need a github-actions update, neither do workflows containing the
+/// `generated_marker`.
+async fn count_gha_workflows(
+    forge: &impl Forge,
+    repository: &Repo,
+    generated_marker: Option<&str>,
+) -> anyhow::Result<usize> {
+    let result = forge
+        .list_directory(
+            &repository.name,
+            ".github/workflows",
+            default_branch(repository),
+        )
+        .await?;
+    let workflows = result.iter().filter(|entry| {
+        !entry.is_dir && (entry.name.ends_with(".yml") || entry.name.ends_with(".yaml"))
+    });
+
+    let Some(marker) = generated_marker else {
+        return Ok(workflows.count());
     };
 
-    let text = content
-        .decoded_content()
-        .context("failed to decode content")?;
+    // Only fetch the workflows when there is a marker to look for.
+    let mut count = 0;
+    for workflow in workflows {
+        let generated = forge
+            .get_file(&repository.name, &workflow.path, default_branch(repository))
+            .await?
+            .and_then(|file| file.content)
+            .is_some_and(|content| content.contains(marker));
+        if generated {
+            log::debug!(
+                "Ignoring generated workflow {} of repo {}",
+                workflow.path,
+                repository.name
+            );
+        } else {
+            count += 1;
+        }
+    }
 
-    let config = serde_yaml_ng::from_str::<DependabotConfig>(&text)?;
-    Ok(Some((config.clone(), content.sha.clone())))
+    Ok(count)
 }
 
-async fn get_dependabot_yml_content(
-    octocrab: &Octocrab,
-    repository: &Repository,
-    branch: &str,
-) -> anyhow::Result<Option<Content>> {
-    let mut result = octocrab
-        .repos("KittyCAD", &repository.name)
-        .get_content()
-        .path(".github/dependabot.yml")
-        .r#ref(branch)
-        .send()
-        .await
-        .context("failed to fetch content")
-        .map(|items| items.items)
-        .unwrap_or_default();
-
-    if result.is_empty() {
-        return Ok(None);
+/// Whether the repo only exists to host a GitHub Pages site. These are either `<org>.github.io`
+/// repos or repos with Pages enabled that have a Jekyll `_config.yml` at the root.
+async fn is_pages_only(forge: &impl Forge, repository: &Repo) -> anyhow::Result<bool> {
+    if repository.name.ends_with(".github.io") {
+        return Ok(true);
     }
 
-    if result.len() != 1 {
-        panic!("found more than one dependabot config")
+    if !repository.has_pages {
+        return Ok(false);
     }
 
-    Ok(Some(result.remove(0)))
+    Ok(forge
+        .get_file(&repository.name, "_config.yml", default_branch(repository))
+        .await?
+        .is_some())
 }
 
-async fn has_gha_config(octocrab: &Octocrab, repository: &Repository) -> anyhow::Result<bool> {
-    let result = octocrab
-        .repos("KittyCAD", &repository.name)
-        .get_content()
-        .path(".github/workflows")
-        .r#ref("main")
-        .send()
-        .await
-        .context("failed to content for GHA check")
-        .map(|items| items.items)
-        .unwrap_or_default();
-
-    if result.is_empty() {
-        Ok(false)
-    } else {
-        Ok(true)
-    }
-}
 async fn search_ecosystems(
     octocrab: &Octocrab,
+    org: &str,
     file: &str,
     content: Option<&str>,
 ) -> anyhow::Result<Vec<Code>> {
     log::info!("Searching for ecosystems using file: {}", file);
 
-    let repos = get_all(octocrab, move |octocrab: &Octocrab, page| {
+    let query = code_search_query(org, file, content);
+    let repos = get_all(octocrab, |octocrab: &Octocrab, page| {
+        let query = query.clone();
         Box::pin({
             async move {
                 octocrab
                     .search()
-                    .code(
-                        format!(
-                            "org:KittyCAD filename:{}{}",
-                            file,
-                            if let Some(content) = content {
-                                format!(" \"{}\"", content)
-                            } else {
-                                String::new()
-                            }
-                        )
-                        .as_str(),
-                    )
+                    .code(&query)
                     .sort("indexed")
                     .order("asc")
                     .per_page(100)
@@ -562,7 +2222,16 @@ async fn search_ecosystems(
     Ok(repos)
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Builds the code search query for `file` in the repos of `org`, e.g.
+/// `org:KittyCAD filename:Cargo.toml "[workspace"`.
+fn code_search_query(org: &str, file: &str, content: Option<&str>) -> String {
+    match content {
+        Some(content) => format!("org:{} filename:{} \"{}\"", org, file, content),
+        None => format!("org:{} filename:{}", org, file),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
 enum Ecosystem {
     Cargo,
     Npm,
@@ -574,6 +2243,16 @@ enum Ecosystem {
     Bundler,
     Docker,
     GitHubActions,
+    Pub,
+    Helm,
+}
+
+impl Ecosystem {
+    /// Whether Dependabot only supports the ecosystem in beta, which needs
+    /// `enable-beta-ecosystems` in the config.
+    fn is_beta(&self) -> bool {
+        matches!(self, Ecosystem::Pub)
+    }
 }
 
 impl Display for Ecosystem {
@@ -589,47 +2268,154 @@ impl Display for Ecosystem {
             Ecosystem::Bundler => write!(f, "bundler")?,
             Ecosystem::Docker => write!(f, "docker")?,
             Ecosystem::GitHubActions => write!(f, "github-actions")?,
+            Ecosystem::Pub => write!(f, "pub")?,
+            Ecosystem::Helm => write!(f, "helm")?,
         }
 
         Ok(())
     }
 }
 
+impl FromStr for Ecosystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ALL: [Ecosystem; 12] = [
+            Ecosystem::Cargo,
+            Ecosystem::Npm,
+            Ecosystem::Go,
+            Ecosystem::Submodule,
+            Ecosystem::Terraform,
+            Ecosystem::Pip,
+            Ecosystem::Uv,
+            Ecosystem::Bundler,
+            Ecosystem::Docker,
+            Ecosystem::GitHubActions,
+            Ecosystem::Pub,
+            Ecosystem::Helm,
+        ];
+
+        ALL.into_iter()
+            .find(|ecosystem| ecosystem.to_string() == s)
+            .ok_or_else(|| {
+                let known = ALL.map(|ecosystem| ecosystem.to_string());
+                format!(
+                    "unknown ecosystem: {s}, expected one of {}",
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+/// How the manifests of the repos are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Detection {
+    /// GitHub code search, cheap but only covers indexed files.
+    Search,
+    /// The recursive git tree of every repo, accurate but needs one request per repo.
+    Tree,
+}
+
+impl FromStr for Detection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "search" => Ok(Detection::Search),
+            "tree" => Ok(Detection::Tree),
+            _ => Err(format!("unknown detection: {s}, expected search or tree")),
+        }
+    }
+}
+
+/// Detects the ecosystems of the org. Code search covers the whole org at once, while the tree
+/// detection only walks the `selected` repos.
+async fn detect_ecosystems(
+    octocrab: &Octocrab,
+    forge: &impl Forge,
+    args: &Args,
+    repos: &[Repo],
+    selected: &[&Repo],
+) -> anyhow::Result<IndexMap<String, Vec<(String, Ecosystem)>>> {
+    Ok(match args.detection {
+        Detection::Search => {
+            find_ecosystems(octocrab, &args.org, repos, args.enable_beta_ecosystems).await?
+        }
+        Detection::Tree => tree::find_ecosystems_in_trees(forge, selected).await,
+    })
+}
+
+/// The detected ecosystems of the `selected` repos.
+fn select_ecosystems(
+    ecosystems: IndexMap<String, Vec<(String, Ecosystem)>>,
+    selected: &[&Repo],
+) -> IndexMap<String, Vec<(String, Ecosystem)>> {
+    ecosystems
+        .into_iter()
+        .filter(|(full_name, _)| selected.iter().any(|repo| repo.full_name == *full_name))
+        .collect()
+}
+
+/// Detects the ecosystems of the org by searching the manifests of all ecosystems in `org`.
+///
+/// Results of repos that are not in `repos`, e.g. forks that code search attributes to the org,
+/// are dropped. Beta ecosystems are only searched for with `enable_beta_ecosystems`, as each
+/// search counts against the search rate limit.
 async fn find_ecosystems(
     octocrab: &Octocrab,
+    org: &str,
+    repos: &[Repo],
+    enable_beta_ecosystems: bool,
 ) -> anyhow::Result<IndexMap<String, Vec<(String, Ecosystem)>>> {
     // TODO Homebrew?
-    // TODO: Handle workspaces (Cargo.toml but maybe also others)
-    let cargo_roots = search_ecosystems(octocrab, "Cargo.toml", Some("[workspace")).await?;
-    let npm_roots = search_ecosystems(octocrab, "package.json", None).await?;
-    let go_roots = search_ecosystems(octocrab, "go.mod", None).await?;
-    let submodule_roots = search_ecosystems(octocrab, ".gitmodules", None).await?;
+    let cargo_roots = search_ecosystems(octocrab, org, "Cargo.toml", Some("[workspace")).await?;
+    let npm_roots = search_ecosystems(octocrab, org, "package.json", None).await?;
+    let go_roots = search_ecosystems(octocrab, org, "go.mod", None).await?;
+    let submodule_roots = search_ecosystems(octocrab, org, ".gitmodules", None).await?;
 
     // avoid rate limits, 9 searches seems max
     sleep(Duration::from_secs(65)).await;
 
-    let python_roots = search_ecosystems(octocrab, "requirements.txt", None).await?;
-    let pyprojects_roots = search_ecosystems(octocrab, "pyproject.toml", None).await?;
-    let bundler_roots = search_ecosystems(octocrab, "Gemfile.lock", None).await?;
-    let docker_roots = search_ecosystems(octocrab, "Dockerfile", None).await?;
+    let python_roots = search_ecosystems(octocrab, org, "requirements.txt", None).await?;
+    let pyprojects_roots = search_ecosystems(octocrab, org, "pyproject.toml", None).await?;
+    let bundler_roots = search_ecosystems(octocrab, org, "Gemfile.lock", None).await?;
+    let docker_roots = search_ecosystems(octocrab, org, "Dockerfile", None)
+        .await?
+        .into_iter()
+        .filter(|code| is_dockerfile(&code.name))
+        .collect::<Vec<_>>();
 
     // avoid rate limits
     sleep(Duration::from_secs(65)).await;
 
-    let terraform_roots = search_ecosystems(octocrab, ".terraform.lock.hcl", None).await?;
-    let uv_roots_1 = search_ecosystems(octocrab, "uv.lock", None).await?;
-    let uv_roots_2 = search_ecosystems(octocrab, "pyproject.toml", Some("tool.uv")).await?;
-    let uv_roots = uv_roots_1
-        .into_iter()
-        .chain(uv_roots_2.into_iter())
-        .collect::<Vec<_>>();
-
-    let pyprojects_roots: Vec<_> = pyprojects_roots
+    let terraform_roots = search_ecosystems(octocrab, org, ".terraform.lock.hcl", None).await?;
+    // No searches for poetry.lock and Pipfile.lock, they only find directories that the
+    // pyproject.toml and Pipfile searches already found.
+    let pipenv_roots = search_ecosystems(octocrab, org, "Pipfile", None).await?;
+    let pub_roots = if enable_beta_ecosystems {
+        search_ecosystems(octocrab, org, "pubspec.yaml", None).await?
+    } else {
+        vec![]
+    };
+    let helm_roots = search_ecosystems(octocrab, org, "Chart.yaml", None).await?;
+    let uv_roots_1 = search_ecosystems(octocrab, org, "uv.lock", None).await?;
+    let uv_roots_2 = search_ecosystems(octocrab, org, "pyproject.toml", Some("tool.uv")).await?;
+    let uv_roots = uv_roots_1.into_iter().chain(uv_roots_2).collect::<Vec<_>>();
+
+    // Dependabot updates pip, pip-compile, pipenv and poetry projects (pyproject.toml with
+    // [tool.poetry] and poetry.lock) all via the pip ecosystem, only uv has its own.
+    // Only drop manifests that live next to a uv project, so that a directory never gets both a
+    // pip and a uv update. Other Python services in the same repo (e.g. services/* in a monorepo)
+    // still need their own pip update.
+    let pip_roots: Vec<_> = [pyprojects_roots, python_roots, pipenv_roots]
+        .concat()
         .into_iter()
         .filter(|root| {
-            !uv_roots
-                .iter()
-                .any(|code| code.repository == root.repository)
+            !uv_roots.iter().any(|code| {
+                code.repository == root.repository
+                    && manifest_directory(&search_result_path(code))
+                        == manifest_directory(&search_result_path(root))
+            })
         })
         .collect();
 
@@ -639,23 +2425,38 @@ async fn find_ecosystems(
         (go_roots, Ecosystem::Go),
         (submodule_roots, Ecosystem::Submodule),
         (terraform_roots, Ecosystem::Terraform),
-        (pyprojects_roots, Ecosystem::Pip),
-        (python_roots, Ecosystem::Pip),
+        (pip_roots, Ecosystem::Pip),
         (uv_roots, Ecosystem::Uv),
         (bundler_roots, Ecosystem::Bundler),
         (docker_roots, Ecosystem::Docker),
+        (pub_roots, Ecosystem::Pub),
+        (helm_roots, Ecosystem::Helm),
     ]
     .iter()
     .flat_map(|(roots, ecosystem)| {
         let mut roots = roots
             .iter()
+            .filter(|code| {
+                let known =
+                    code.repository.full_name.as_ref().is_some_and(|full_name| {
+                        repos.iter().any(|repo| repo.full_name == *full_name)
+                    });
+                if !known {
+                    log::debug!(
+                        "Ignoring {} in {:?}, which is not a repo of the org",
+                        code.path,
+                        code.repository.full_name
+                    );
+                }
+                known
+            })
             .map(move |code| {
                 (
                     code.repository
                         .full_name
                         .clone()
                         .expect("full_name must be available"),
-                    (code.url.path().to_string(), *ecosystem),
+                    (search_result_path(code), *ecosystem),
                 )
             })
             .collect::<Vec<_>>();
@@ -663,9 +2464,903 @@ async fn find_ecosystems(
         roots
     })
     .fold(IndexMap::new(), |mut acc, (repo, entry)| {
-        acc.entry(repo).or_default().push(entry);
+        add_manifest(acc.entry(repo).or_default(), entry);
         acc
     });
 
     Ok(ecosystems)
 }
+
+/// The API path of the file of a code search result, e.g.
+/// `/repositories/848456627/contents/services/api/go.mod`, which is the format of all detected
+/// manifests. It is built from the repo-relative `path` of the result instead of its `url`, as the
+/// path of the URL is percent-encoded and has a different prefix on GitHub Enterprise Server, e.g.
+/// `/api/v3/repositories/...`.
+fn search_result_path(code: &Code) -> String {
+    format!(
+        "/repositories/{}/contents/{}",
+        code.repository.id,
+        code.path.trim_start_matches('/')
+    )
+}
+
+/// Whether the file is a `Dockerfile` or a variant like `Dockerfile.ci`. Dependabot updates all of
+/// them with one docker update for their directory, see [`add_manifest`].
+fn is_dockerfile(file_name: &str) -> bool {
+    file_name == "Dockerfile" || file_name.starts_with("Dockerfile.")
+}
+
+/// Adds a detected manifest to the entries of its repo. Several manifests of one ecosystem in the
+/// same directory (e.g. pyproject.toml, poetry.lock and requirements.txt) map to a single update.
+fn add_manifest(entries: &mut Vec<(String, Ecosystem)>, entry: (String, Ecosystem)) {
+    if !entries.iter().any(|(path, ecosystem)| {
+        *ecosystem == entry.1 && manifest_directory(path) == manifest_directory(&entry.0)
+    }) {
+        entries.push(entry);
+    }
+}
+
+/// Turns the API path of a code search result into the directory of the manifest within the repo.
+///
+/// Nested manifests keep their full directory, which is also the module root Dependabot expects
+/// for `gomod`, e.g. `/repositories/848456627/contents/cmd/tool/go.mod` becomes `/cmd/tool` and
+/// `/repositories/848456627/contents/go.mod` becomes `/`.
+fn manifest_directory(path: &str) -> String {
+    match manifest_path(path).rsplit_once('/') {
+        Some((directory, _)) => format!("/{directory}"),
+        None => "/".to_string(),
+    }
+}
+
+/// The path within the repo of the API path of a detected manifest, i.e. everything after its
+/// `contents/` segment. `None` if there is no such segment or the rest is no plausible file path,
+/// e.g. because it is empty or has `..` segments.
+fn repo_relative_path(path: &str) -> Option<&str> {
+    let (_, path) = path.split_once("/contents/")?;
+    path.split('/')
+        .all(|segment| !matches!(segment, "" | "." | ".."))
+        .then_some(path)
+}
+
+/// Drops manifests whose API path has no plausible path within the repo, e.g. from a cache
+/// written for another URL format, as they would end up as wrong directories in the config.
+fn drop_malformed_manifests(
+    ecosystems: IndexMap<String, Vec<(String, Ecosystem)>>,
+) -> IndexMap<String, Vec<(String, Ecosystem)>> {
+    ecosystems
+        .into_iter()
+        .map(|(repo, manifests)| {
+            let manifests = manifests
+                .into_iter()
+                .filter(|(path, ecosystem)| {
+                    let valid = repo_relative_path(path).is_some();
+                    if !valid {
+                        log::warn!(
+                            "Ignoring {} manifest of {} with the malformed path {}",
+                            ecosystem,
+                            repo,
+                            path
+                        );
+                    }
+                    valid
+                })
+                .collect();
+            (repo, manifests)
+        })
+        .collect()
+}
+
+/// Fetches the manifest at the API path of a code search result from the default branch, `None`
+/// if it has no text content.
+async fn fetch_manifest(
+    forge: &impl Forge,
+    repo: &Repo,
+    path: &str,
+) -> anyhow::Result<Option<String>> {
+    let file = forge
+        .get_file(&repo.name, &manifest_path(path), default_branch(repo))
+        .await?;
+
+    Ok(file.and_then(|file| file.content))
+}
+
+/// Turns the API path of a code search result into the path of the manifest within the repo, e.g.
+/// `/repositories/848456627/contents/cmd/tool/go.mod` becomes `cmd/tool/go.mod`. Malformed paths
+/// are dropped after detection, see [`drop_malformed_manifests`].
+fn manifest_path(path: &str) -> String {
+    repo_relative_path(path).unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_forge::FakeForge;
+
+    /// A manifest at `path` of the repo, in the format of the detection.
+    fn manifest(path: &str, ecosystem: Ecosystem) -> (String, Ecosystem) {
+        (format!("/repositories/1/contents/{path}"), ecosystem)
+    }
+
+    /// Renders the config of a repo with the given level as it would be committed.
+    fn render(
+        repo: &str,
+        level: AssetLevel,
+        gha_directories: &[(&str, &str)],
+        manifests: &[(String, Ecosystem)],
+        overrides: &DependabotOverrides,
+    ) -> String {
+        let policy = Policy::for_level(level, &overrides.defaults);
+        let gha_directories = gha_directories
+            .iter()
+            .map(|(directory, detected_from)| (directory.to_string(), detected_from.to_string()))
+            .collect();
+        let config = build_config(
+            repo,
+            "main",
+            gha_directories,
+            manifests,
+            overrides,
+            &policy,
+            3,
+        )
+        .unwrap();
+
+        render_config(&config, &overrides.defaults).unwrap()
+    }
+
+    const WORKFLOWS: (&str, &str) = ("/", ".github/workflows");
+
+    #[test]
+    fn golden_cargo_workspace() {
+        let manifests = [
+            manifest("crates/api/Cargo.toml", Ecosystem::Cargo),
+            manifest("crates/cli/Cargo.toml", Ecosystem::Cargo),
+        ];
+        let config = render(
+            "engine",
+            AssetLevel::Production,
+            &[WORKFLOWS],
+            &manifests,
+            &DependabotOverrides::default(),
+        );
+
+        assert_eq!(
+            config,
+            include_str!("../tests/fixtures/cargo_workspace.yml")
+        );
+    }
+
+    #[test]
+    fn golden_npm_monorepo() {
+        let manifests = [
+            manifest("package.json", Ecosystem::Npm),
+            manifest("packages/api/package.json", Ecosystem::Npm),
+            manifest("packages/ui/package.json", Ecosystem::Npm),
+            manifest("packages/web/package.json", Ecosystem::Npm),
+        ];
+        let config = render(
+            "website",
+            AssetLevel::NonEssentialProduction,
+            &[WORKFLOWS],
+            &manifests,
+            &DependabotOverrides::default(),
+        );
+
+        assert_eq!(config, include_str!("../tests/fixtures/npm_monorepo.yml"));
+    }
+
+    #[test]
+    fn golden_terraform_modules() {
+        let manifests = [
+            manifest("modules/dns/.terraform.lock.hcl", Ecosystem::Terraform),
+            manifest("modules/network/.terraform.lock.hcl", Ecosystem::Terraform),
+        ];
+        let config = render(
+            "infra",
+            AssetLevel::Corporate,
+            &[],
+            &manifests,
+            &DependabotOverrides::default(),
+        );
+
+        assert_eq!(
+            config,
+            include_str!("../tests/fixtures/terraform_modules.yml")
+        );
+    }
+
+    #[test]
+    fn golden_actions_docker_submodules() {
+        let manifests = [
+            manifest("Dockerfile", Ecosystem::Docker),
+            manifest("docker/Dockerfile.ci", Ecosystem::Docker),
+            manifest(".gitmodules", Ecosystem::Submodule),
+        ];
+        let config = render(
+            "modeling-app",
+            AssetLevel::ResearchNDevelopment,
+            &[
+                WORKFLOWS,
+                ("/.github/actions/setup", ".github/actions/setup/action.yml"),
+            ],
+            &manifests,
+            &DependabotOverrides::default(),
+        );
+
+        assert_eq!(
+            config,
+            include_str!("../tests/fixtures/actions_docker_submodules.yml")
+        );
+    }
+
+    #[test]
+    fn golden_override_applied() {
+        let overrides: DependabotOverrides = toml::from_str(
+            r#"
+            [[updates.docs]]
+            package-ecosystem = "npm"
+            open-pull-requests-limit = 10
+            schedule = { interval = "monthly" }
+            "#,
+        )
+        .unwrap();
+        let manifests = [
+            manifest("package.json", Ecosystem::Npm),
+            manifest("requirements.txt", Ecosystem::Pip),
+        ];
+        let config = render(
+            "docs",
+            AssetLevel::Corporate,
+            &[WORKFLOWS],
+            &manifests,
+            &overrides,
+        );
+
+        assert_eq!(
+            config,
+            include_str!("../tests/fixtures/override_applied.yml")
+        );
+    }
+
+    #[test]
+    fn golden_kittycad_exclusions() {
+        let overrides: DependabotOverrides =
+            toml::from_str(include_str!("../overrides-sample.toml")).unwrap();
+        let manifests = [manifest("Cargo.toml", Ecosystem::Cargo)];
+        let config = render(
+            "cli",
+            AssetLevel::Production,
+            &[WORKFLOWS],
+            &manifests,
+            &overrides,
+        );
+
+        assert_eq!(
+            config,
+            include_str!("../tests/fixtures/kittycad_exclusions.yml")
+        );
+    }
+
+    fn args(flags: &[&str]) -> Args {
+        let args = [&["KittyCAD"], flags].concat();
+        Args::from_args(&["dependabot-org-config"], &args).unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_selected_virtual_workspaces_are_expanded() {
+        let workspace = "[workspace]\nmembers = [\"crates/api\", \"crates/cli\"]\n";
+        let forge = FakeForge::default()
+            .with_repo("engine", AssetLevel::Production)
+            .with_repo("website", AssetLevel::Corporate)
+            .with_file("engine", "Cargo.toml", workspace)
+            .with_file("website", "Cargo.toml", workspace);
+        let ecosystems = IndexMap::from([
+            (
+                "KittyCAD/engine".to_string(),
+                vec![manifest("Cargo.toml", Ecosystem::Cargo)],
+            ),
+            (
+                "KittyCAD/website".to_string(),
+                vec![manifest("Cargo.toml", Ecosystem::Cargo)],
+            ),
+        ]);
+        let selected = [forge.repo("engine")];
+
+        let ecosystems = select_ecosystems(ecosystems, &selected);
+        let ecosystems = cargo::expand_virtual_workspaces(&forge, &selected, ecosystems, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ecosystems,
+            IndexMap::from([(
+                "KittyCAD/engine".to_string(),
+                vec![
+                    manifest("crates/api/Cargo.toml", Ecosystem::Cargo),
+                    manifest("crates/cli/Cargo.toml", Ecosystem::Cargo),
+                ],
+            )])
+        );
+    }
+
+    #[tokio::test]
+    async fn only_selected_dependencyless_packages_are_skipped() {
+        let forge = FakeForge::default()
+            .with_repo("engine", AssetLevel::Production)
+            .with_repo("website", AssetLevel::Corporate)
+            .with_file("engine", "package.json", "{\"private\": true}")
+            .with_file("website", "package.json", "{\"private\": true}");
+        let ecosystems = IndexMap::from([
+            (
+                "KittyCAD/engine".to_string(),
+                vec![
+                    manifest("package.json", Ecosystem::Npm),
+                    manifest("Cargo.toml", Ecosystem::Cargo),
+                ],
+            ),
+            (
+                "KittyCAD/website".to_string(),
+                vec![manifest("package.json", Ecosystem::Npm)],
+            ),
+        ]);
+        let selected = [forge.repo("engine")];
+
+        let ecosystems = select_ecosystems(ecosystems, &selected);
+        let ecosystems = npm::skip_dependencyless_packages(&forge, &selected, ecosystems)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ecosystems,
+            IndexMap::from([(
+                "KittyCAD/engine".to_string(),
+                vec![manifest("Cargo.toml", Ecosystem::Cargo)],
+            )])
+        );
+    }
+
+    #[test]
+    fn pr_names_each_ecosystem_once() {
+        let update = |ecosystem: &str, directory: &str| Update {
+            package_ecosystem: ecosystem.to_string(),
+            directory: Some(directory.to_string()),
+            ..Update::default()
+        };
+        let config = DependabotConfig {
+            version: 2,
+            updates: vec![
+                update("cargo", "/crates/api"),
+                update("npm", "/"),
+                update("cargo", "/crates/cli"),
+            ],
+            ..DependabotConfig::default()
+        };
+
+        assert_eq!(pr_ecosystems(&config), ["cargo", "npm"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_overrides() {
+        for (overrides, ecosystem) in [
+            (
+                r#"
+                [[updates.cli]]
+                schedule = { interval = "daily" }
+                [[updates.cli]]
+                open-pull-requests-limit = 10
+                "#,
+                "*",
+            ),
+            (
+                r#"
+                [[updates.cli]]
+                package-ecosystem = "cargo"
+                [[updates.cli]]
+                schedule = { interval = "daily" }
+                [[updates.cli]]
+                package-ecosystem = "cargo"
+                "#,
+                "cargo",
+            ),
+        ] {
+            let overrides: DependabotOverrides = toml::from_str(overrides).unwrap();
+
+            assert_eq!(
+                overrides.validate_updates().unwrap_err().to_string(),
+                format!("repo cli has more than one override for ecosystem {ecosystem}")
+            );
+        }
+
+        let overrides: DependabotOverrides =
+            toml::from_str(include_str!("../overrides-sample.toml")).unwrap();
+        overrides.validate_updates().unwrap();
+    }
+
+    #[test]
+    fn filtered_tree_detection_is_not_cached() {
+        assert!(args(&[]).detects_whole_org());
+        assert!(args(&["--detection", "tree"]).detects_whole_org());
+        assert!(args(&["--repo", "engine"]).detects_whole_org());
+        assert!(!args(&["--detection", "tree", "--repo", "engine"]).detects_whole_org());
+        assert!(!args(&["--detection", "tree", "--include", "kcl-*"]).detects_whole_org());
+        assert!(!args(&["--detection", "tree", "--exclude", "kcl-*"]).detects_whole_org());
+        assert!(
+            !args(&["--detection", "tree", "--since", "2025-01-01T00:00:00Z"]).detects_whole_org()
+        );
+    }
+
+    fn writes() -> Writes {
+        Writes {
+            permits: Semaphore::new(1),
+            pr_throttle: PrThrottle::new(None, Duration::ZERO),
+            limit: WriteLimit::new(None),
+            new_prs: WriteLimit::new(None),
+            prompt: Prompt::new(false),
+        }
+    }
+
+    /// The repo of `golden_cargo_workspace` on a fake GitHub, with a workflow and the detected
+    /// workspace members.
+    fn engine() -> (FakeForge, IndexMap<String, Vec<(String, Ecosystem)>>) {
+        let forge = FakeForge::default()
+            .with_repo("engine", AssetLevel::Production)
+            .with_file("engine", ".github/workflows/ci.yml", "on: push\n");
+        let ecosystems = IndexMap::from([(
+            "KittyCAD/engine".to_string(),
+            vec![
+                manifest("crates/api/Cargo.toml", Ecosystem::Cargo),
+                manifest("crates/cli/Cargo.toml", Ecosystem::Cargo),
+            ],
+        )]);
+
+        (forge, ecosystems)
+    }
+
+    async fn process(
+        forge: &FakeForge,
+        args: &Args,
+        ecosystems: &IndexMap<String, Vec<(String, Ecosystem)>>,
+    ) -> RepoStatus {
+        let state = StateFile::open(None).unwrap();
+        let report = process_repo(
+            forge,
+            args,
+            &DependabotOverrides::default(),
+            ecosystems,
+            &writes(),
+            &state,
+            forge.repo("engine"),
+        )
+        .await
+        .unwrap();
+
+        report.status
+    }
+
+    #[tokio::test]
+    async fn creates_pr_with_generated_config() {
+        let (forge, ecosystems) = engine();
+        let args = args(&["--create-pr", "--force-new"]);
+
+        assert_eq!(
+            process(&forge, &args, &ecosystems).await,
+            RepoStatus::Created
+        );
+        assert_eq!(
+            forge.calls(),
+            [
+                "create_branch engine ciso/update-dependabot from main",
+                "create_file engine ciso/update-dependabot .github/dependabot.yml",
+                "open_pr engine ciso/update-dependabot -> main",
+            ]
+        );
+        assert_eq!(
+            forge
+                .file("engine", "ciso/update-dependabot", ".github/dependabot.yml")
+                .unwrap(),
+            include_str!("../tests/fixtures/cargo_workspace.yml")
+        );
+    }
+
+    #[tokio::test]
+    async fn second_run_leaves_open_pr_unchanged() {
+        let (forge, ecosystems) = engine();
+        let args = args(&["--create-pr", "--force-new"]);
+
+        process(&forge, &args, &ecosystems).await;
+        let calls = forge.calls();
+
+        assert_eq!(
+            process(&forge, &args, &ecosystems).await,
+            RepoStatus::Unchanged
+        );
+        assert_eq!(forge.calls(), calls);
+    }
+
+    #[tokio::test]
+    async fn dry_run_does_not_write() {
+        let (forge, ecosystems) = engine();
+        let forge = forge.with_file(
+            "engine",
+            ".github/dependabot.yml",
+            &(PolicyDefaults::default().header() + "version: 2\nupdates: []\n"),
+        );
+
+        assert_eq!(
+            process(&forge, &args(&[]), &ecosystems).await,
+            RepoStatus::WouldUpdate
+        );
+        assert!(forge.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn skips_hand_written_config() {
+        let (forge, ecosystems) = engine();
+        let forge = forge.with_file(
+            "engine",
+            ".github/dependabot.yml",
+            "version: 2\nupdates: []\n",
+        );
+
+        assert_eq!(
+            process(&forge, &args(&["--create-pr"]), &ecosystems).await,
+            RepoStatus::Skipped
+        );
+        assert!(forge.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn skips_playground_repo() {
+        let forge = FakeForge::default().with_repo("engine", AssetLevel::Playground);
+
+        assert_eq!(
+            process(
+                &forge,
+                &args(&["--create-pr", "--force-new"]),
+                &IndexMap::new()
+            )
+            .await,
+            RepoStatus::Skipped
+        );
+        assert!(forge.calls().is_empty());
+    }
+
+    /// The config of a production repo without workflows or overrides.
+    fn config(manifests: &[(String, Ecosystem)]) -> DependabotConfig {
+        let overrides = DependabotOverrides::default();
+        let policy = Policy::for_level(AssetLevel::Production, &overrides.defaults);
+
+        build_config("engine", "main", vec![], manifests, &overrides, &policy, 3).unwrap()
+    }
+
+    /// The directories of the updates of `ecosystem`.
+    fn directories(config: &DependabotConfig, ecosystem: &str) -> Vec<String> {
+        config
+            .updates
+            .iter()
+            .filter(|update| update.package_ecosystem == ecosystem)
+            .map(|update| update.directory.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn nested_go_module_gets_own_update() {
+        let config = config(&[
+            manifest("go.mod", Ecosystem::Go),
+            manifest("cmd/tool/go.mod", Ecosystem::Go),
+        ]);
+
+        assert_eq!(directories(&config, "gomod"), ["/", "/cmd/tool"]);
+    }
+
+    #[test]
+    fn manifest_paths_of_contents_api_paths() {
+        let path = "/repositories/848456627/contents/cmd/tool/go.mod";
+        assert_eq!(repo_relative_path(path), Some("cmd/tool/go.mod"));
+        assert_eq!(manifest_path(path), "cmd/tool/go.mod");
+        assert_eq!(manifest_directory(path), "/cmd/tool");
+
+        let root = "/repositories/848456627/contents/Cargo.toml";
+        assert_eq!(manifest_path(root), "Cargo.toml");
+        assert_eq!(manifest_directory(root), "/");
+
+        // Only the first `contents` segment separates the repo from the path within it.
+        let nested = "/repositories/848456627/contents/docs/contents/package.json";
+        assert_eq!(manifest_directory(nested), "/docs/contents");
+    }
+
+    #[test]
+    fn malformed_contents_api_paths() {
+        for path in [
+            "/repositories/848456627/go.mod",
+            "/repositories/848456627/contents/",
+            "/repositories/848456627/contents//go.mod",
+            "/repositories/848456627/contents/cmd/../go.mod",
+            "/repositories/848456627/contents/./go.mod",
+            "https://api.github.com/repositories/848456627/contents%2Fgo.mod",
+        ] {
+            assert_eq!(repo_relative_path(path), None, "{path}");
+            assert_eq!(manifest_path(path), "", "{path}");
+        }
+
+        let ecosystems = drop_malformed_manifests(IndexMap::from([(
+            "KittyCAD/engine".to_string(),
+            vec![
+                manifest("go.mod", Ecosystem::Go),
+                (
+                    "/repositories/1/contents/../go.mod".to_string(),
+                    Ecosystem::Go,
+                ),
+            ],
+        )]));
+        assert_eq!(
+            ecosystems["KittyCAD/engine"],
+            [manifest("go.mod", Ecosystem::Go)]
+        );
+    }
+
+    /// A code search result for `path` with the given API `url`.
+    fn code(path: &str, url: &str) -> Code {
+        serde_json::from_value(serde_json::json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "sha": "0000000000000000000000000000000000000000",
+            "url": url,
+            "git_url": "https://api.github.com/repositories/848456627/git/blobs/0",
+            "html_url": "https://github.com/KittyCAD/engine/blob/main/go.mod",
+            "repository": {
+                "id": 848456627,
+                "name": "engine",
+                "full_name": "KittyCAD/engine",
+                "url": "https://api.github.com/repos/KittyCAD/engine",
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn search_result_path_ignores_url_shape() {
+        for url in [
+            "https://api.github.com/repositories/848456627/contents/cmd/tool/go.mod?ref=abc",
+            "https://github.example.com/api/v3/repositories/848456627/contents/cmd/tool/go.mod?ref=abc",
+            "https://api.github.com/repositories/848456627/contents/cmd%2Ftool%2Fgo.mod?ref=abc",
+        ] {
+            assert_eq!(
+                search_result_path(&code("cmd/tool/go.mod", url)),
+                "/repositories/848456627/contents/cmd/tool/go.mod",
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn search_result_path_of_root_and_special_characters() {
+        let url = "https://api.github.com/repositories/848456627/contents/go.mod?ref=abc";
+        assert_eq!(
+            search_result_path(&code("go.mod", url)),
+            "/repositories/848456627/contents/go.mod"
+        );
+
+        let url =
+            "https://api.github.com/repositories/848456627/contents/my%20app/package.json?ref=abc";
+        let path = search_result_path(&code("my app/package.json", url));
+        assert_eq!(path, "/repositories/848456627/contents/my app/package.json");
+        assert_eq!(manifest_directory(&path), "/my app");
+    }
+
+    #[test]
+    fn nested_python_services_get_own_updates() {
+        let mut manifests = vec![];
+        for (path, ecosystem) in [
+            ("services/api/pyproject.toml", Ecosystem::Pip),
+            ("services/api/poetry.lock", Ecosystem::Pip),
+            ("services/worker/requirements.txt", Ecosystem::Pip),
+            ("services/worker/Pipfile.lock", Ecosystem::Pip),
+        ] {
+            add_manifest(&mut manifests, manifest(path, ecosystem));
+        }
+        let config = config(&manifests);
+
+        assert_eq!(
+            directories(&config, "pip"),
+            ["/services/api", "/services/worker"]
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_only_repos() {
+        let forge = FakeForge::default()
+            .with_repo("KittyCAD.github.io", AssetLevel::Corporate)
+            .with_repo("docs", AssetLevel::Corporate)
+            .with_file("docs", "_config.yml", "theme: minima\n")
+            .with_repo("website", AssetLevel::Corporate)
+            .with_file("website", "package.json", "{}\n");
+        let with_pages = |name: &str| Repo {
+            has_pages: true,
+            ..forge.repo(name).clone()
+        };
+
+        assert!(
+            is_pages_only(&forge, forge.repo("KittyCAD.github.io"))
+                .await
+                .unwrap()
+        );
+        assert!(is_pages_only(&forge, &with_pages("docs")).await.unwrap());
+        // Pages without Jekyll, e.g. built by a workflow, and a Jekyll config without Pages.
+        assert!(!is_pages_only(&forge, &with_pages("website")).await.unwrap());
+        assert!(!is_pages_only(&forge, forge.repo("docs")).await.unwrap());
+    }
+
+    /// Processes the repos concurrently, like `run` does, and returns how many files were being
+    /// created at the same time at most.
+    async fn max_concurrent_writes(write_concurrency: usize) -> usize {
+        let names = ["api", "cli", "engine", "docs", "website"];
+        let mut forge = FakeForge::default();
+        let mut ecosystems = IndexMap::new();
+        for name in names {
+            forge = forge.with_repo(name, AssetLevel::Production);
+            ecosystems.insert(
+                format!("KittyCAD/{name}"),
+                vec![manifest("Cargo.toml", Ecosystem::Cargo)],
+            );
+        }
+        let args = args(&["--create-pr", "--force-new"]);
+        let writes = Writes {
+            permits: Semaphore::new(write_concurrency),
+            ..writes()
+        };
+        let state = StateFile::open(None).unwrap();
+        let overrides = DependabotOverrides::default();
+
+        let reports = future::join_all(names.iter().map(|name| {
+            process_repo(
+                &forge,
+                &args,
+                &overrides,
+                &ecosystems,
+                &writes,
+                &state,
+                forge.repo(name),
+            )
+        }))
+        .await;
+        for report in reports {
+            assert_eq!(report.unwrap().status, RepoStatus::Created);
+        }
+
+        forge.max_concurrent_writes()
+    }
+
+    #[tokio::test]
+    async fn writes_respect_write_concurrency() {
+        assert_eq!(max_concurrent_writes(1).await, 1);
+        assert_eq!(max_concurrent_writes(2).await, 2);
+    }
+
+    #[test]
+    fn repo_override_applies_to_all_ecosystems_unless_overridden() {
+        let overrides: DependabotOverrides = toml::from_str(
+            r#"
+            [[updates.engine]]
+            schedule = { interval = "monthly" }
+
+            [[updates.engine]]
+            package-ecosystem = "cargo"
+            schedule = { interval = "weekly", day = "monday" }
+            "#,
+        )
+        .unwrap();
+        let policy = Policy::for_level(AssetLevel::Production, &overrides.defaults);
+        let config = build_config(
+            "engine",
+            "main",
+            vec![("/".to_string(), ".github/workflows".to_string())],
+            &[
+                manifest("Cargo.toml", Ecosystem::Cargo),
+                manifest("web/package.json", Ecosystem::Npm),
+            ],
+            &overrides,
+            &policy,
+            3,
+        )
+        .unwrap();
+
+        let schedules = config
+            .updates
+            .iter()
+            .map(|update| (update.package_ecosystem.as_str(), update.schedule.slot()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            schedules,
+            [
+                ("cargo", "weekly monday".to_string()),
+                ("github-actions", "monthly".to_string()),
+                ("npm", "monthly".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn yaml_is_identical_regardless_of_detection_order() {
+        let manifests = vec![
+            manifest("web/package.json", Ecosystem::Npm),
+            manifest("Cargo.toml", Ecosystem::Cargo),
+            manifest("Dockerfile", Ecosystem::Docker),
+            manifest("api/go.mod", Ecosystem::Go),
+            manifest("package.json", Ecosystem::Npm),
+        ];
+        let mut reversed = manifests.clone();
+        reversed.reverse();
+        let overrides = DependabotOverrides::default();
+        let render = |manifests: &[(String, Ecosystem)]| {
+            render(
+                "engine",
+                AssetLevel::Production,
+                &[WORKFLOWS],
+                manifests,
+                &overrides,
+            )
+        };
+
+        let first = render(&manifests);
+        assert_eq!(first, render(&manifests));
+        assert_eq!(first, render(&reversed));
+    }
+
+    #[test]
+    fn code_search_query_uses_org() {
+        assert_eq!(
+            code_search_query("acme", "go.mod", None),
+            "org:acme filename:go.mod"
+        );
+        assert_eq!(
+            code_search_query("acme", "Cargo.toml", Some("[workspace")),
+            "org:acme filename:Cargo.toml \"[workspace\""
+        );
+    }
+
+    #[test]
+    fn dockerfile_variants_share_one_update() {
+        assert!(is_dockerfile("Dockerfile"));
+        assert!(is_dockerfile("Dockerfile.ci"));
+        assert!(!is_dockerfile("Dockerfile-old"));
+        assert!(!is_dockerfile("docker-compose.yml"));
+
+        let config = config(&[
+            manifest("docker/Dockerfile", Ecosystem::Docker),
+            manifest("docker/Dockerfile.ci", Ecosystem::Docker),
+        ]);
+
+        assert_eq!(directories(&config, "docker"), ["/docker"]);
+        assert_eq!(
+            config.updates[0].detected_from,
+            ["docker/Dockerfile", "docker/Dockerfile.ci"]
+        );
+    }
+
+    #[test]
+    fn python_manifests_of_one_directory_share_one_update() {
+        let config = config(&[
+            manifest("api/requirements.txt", Ecosystem::Pip),
+            manifest("api/pyproject.toml", Ecosystem::Pip),
+            manifest("api/poetry.lock", Ecosystem::Pip),
+        ]);
+
+        assert_eq!(directories(&config, "pip"), ["/api"]);
+        assert_eq!(
+            config.updates[0].detected_from,
+            [
+                "api/requirements.txt",
+                "api/pyproject.toml",
+                "api/poetry.lock"
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_gitmodules_map_to_root() {
+        let config = config(&[manifest("vendor/lib/.gitmodules", Ecosystem::Submodule)]);
+
+        assert_eq!(directories(&config, "gitsubmodule"), ["/"]);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::forge::{Forge, Repo};
+use crate::{Ecosystem, fetch_manifest, manifest_path};
+use indexmap::IndexMap;
+
+/// Drops the npm manifest of repos whose only package.json declares no dependencies, e.g. a shim
+/// that only configures tooling. Dependabot has nothing to update there.
+///
+/// Repos with several package.json files are kept as they are, a root without dependencies can
+/// still be needed for workspaces. Only the manifests of `repos` are read.
+pub async fn skip_dependencyless_packages(
+    forge: &impl Forge,
+    repos: &[&Repo],
+    mut ecosystems: IndexMap<String, Vec<(String, Ecosystem)>>,
+) -> anyhow::Result<IndexMap<String, Vec<(String, Ecosystem)>>> {
+    for (full_name, entries) in &mut ecosystems {
+        let mut npm_entries = entries
+            .iter()
+            .filter(|(_, ecosystem)| *ecosystem == Ecosystem::Npm);
+        let (Some((path, _)), None) = (npm_entries.next(), npm_entries.next()) else {
+            continue;
+        };
+        let Some(repo) = repos.iter().find(|repo| repo.full_name == *full_name) else {
+            continue;
+        };
+
+        // Keep the manifest if it cannot be read, as before.
+        let has_dependencies = match fetch_manifest(forge, repo, path).await {
+            Ok(Some(content)) => has_dependencies(&content),
+            Ok(None) => true,
+            Err(e) => {
+                log::warn!(
+                    "Failed to read {} of {}: {:#}",
+                    manifest_path(path),
+                    repo.name,
+                    e
+                );
+                true
+            }
+        };
+        if has_dependencies {
+            continue;
+        }
+
+        log::info!(
+            "Skipping npm for repo {} as its only {} has no dependencies or devDependencies",
+            repo.name,
+            manifest_path(path)
+        );
+        entries.retain(|(_, ecosystem)| *ecosystem != Ecosystem::Npm);
+    }
+
+    Ok(ecosystems)
+}
+
+/// Whether the package.json declares any dependencies or devDependencies. Unparsable manifests
+/// count as having some, so that they are not skipped by accident.
+fn has_dependencies(content: &str) -> bool {
+    let Ok(manifest) = serde_json::from_str::<serde_json::Value>(content) else {
+        return true;
+    };
+
+    ["dependencies", "devDependencies"].iter().any(|key| {
+        manifest
+            .get(key)
+            .and_then(|dependencies| dependencies.as_object())
+            .is_some_and(|dependencies| !dependencies.is_empty())
+    })
+}
@@ -0,0 +1,439 @@
+use crate::dependabot::{CommitMessage, Cooldown, DependencyRule, Group, Registry, Schedule};
+use crate::github::AssetLevel;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Org specific defaults, read from the `[defaults]` section of the overrides file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct PolicyDefaults {
+    /// Dependencies excluded from the security, patch and minor groups (supports wildcards).
+    pub group_exclude_patterns: Vec<String>,
+    /// Dependencies excluded from the cooldown (supports wildcards).
+    pub cooldown_exclude: Vec<String>,
+    /// Package ecosystems (e.g. "docker") whose updates get no cooldown at all.
+    pub no_cooldown_ecosystems: Vec<String>,
+    /// Replaces the default schedule of every asset level, including the daily one of Production.
+    pub schedule: Option<Schedule>,
+    /// Merged into the default cooldown per field. The asset levels still set their own
+    /// default-days.
+    pub cooldown: Option<Cooldown>,
+    /// Reviewers of the PRs Dependabot opens.
+    pub reviewers: Vec<String>,
+    /// Labels of the PRs Dependabot opens.
+    pub labels: Vec<String>,
+    /// Group minor and patch updates of development dependencies separately from production ones.
+    pub group_development_dependencies: bool,
+    /// Groups added after the generated ones, e.g. to update related crates together.
+    pub extra_groups: IndexMap<String, Group>,
+    /// Private registries that are generated for every update of the matching ecosystems.
+    pub registries: IndexMap<String, RegistryTemplate>,
+    /// Open pull request limits by package ecosystem (e.g. "npm"), replacing the global limit.
+    pub open_pull_requests_limits: IndexMap<String, u32>,
+    /// Commit messages by package ecosystem (e.g. "cargo").
+    pub commit_messages: IndexMap<String, CommitMessage>,
+    /// Versioning strategies by package ecosystem (e.g. "npm").
+    pub versioning_strategies: IndexMap<String, String>,
+    /// Internal packages by package ecosystem (supports wildcards), which Dependabot ignores
+    /// completely as the release automation updates them.
+    pub internal_packages: IndexMap<String, Vec<String>>,
+    /// Allow rules by package ecosystem, e.g. to only update direct dependencies of "gomod".
+    pub allow: IndexMap<String, Vec<DependencyRule>>,
+    /// Package ecosystems (e.g. "bundler") whose updates may execute external code, which
+    /// Dependabot denies by default.
+    pub insecure_external_code_execution: Vec<String>,
+    /// Repos (supports wildcards) that only have generated or vendored workflows, so they get no
+    /// github-actions update at all.
+    pub generated_workflow_repos: Vec<String>,
+    /// Workflows containing this marker, e.g. a "generated by" comment, don't need a
+    /// github-actions update.
+    pub generated_workflow_marker: Option<String>,
+    /// Title of the bot PR, supports the `{repo}`, `{ecosystems}`, `{source_repo}` and
+    /// `{maintainer}` placeholders.
+    pub pr_title: Option<String>,
+    /// Body of the bot PR, supports the same placeholders as the title.
+    pub pr_body: Option<String>,
+    /// The repo (e.g. "KittyCAD/ciso") the configs are generated from, referenced by the header,
+    /// commit messages and PRs.
+    pub source_repo: Option<String>,
+    /// GitHub user to contact about the generated configs, without the "@".
+    pub maintainer: Option<String>,
+    /// Comment at the top of every generated config, supports the `{source_repo}`,
+    /// `{source_repo_name}` and `{maintainer}` placeholders. Configs starting with it are
+    /// considered generated by this tool.
+    pub header: Option<String>,
+    /// Package ecosystem generated for Helm charts (`Chart.yaml`), "helm" unless Dependabot
+    /// renames it again.
+    pub helm_ecosystem: Option<String>,
+}
+
+const DEFAULT_PR_TITLE: &str = "Update dependabot config";
+const DEFAULT_PR_BODY: &str = "This PR was automatically generated from {source_repo} for the {ecosystems} ecosystems of {repo}. Please merge this soon.";
+const DEFAULT_SOURCE_REPO: &str = "KittyCAD/ciso";
+const DEFAULT_HELM_ECOSYSTEM: &str = "helm";
+const DEFAULT_HEADER: &str = "# DO NOT EDIT THIS FILE. This dependabot file was generated \n\
+                              # by https://github.com/{source_repo} Changes to this file should be addressed in \n\
+                              # the {source_repo_name} repository.";
+
+impl PolicyDefaults {
+    pub fn pr_title(&self, repo: &str, ecosystems: &[String]) -> String {
+        self.fill_placeholders(
+            self.pr_title.as_deref().unwrap_or(DEFAULT_PR_TITLE),
+            repo,
+            ecosystems,
+        )
+    }
+
+    pub fn pr_body(&self, repo: &str, ecosystems: &[String]) -> String {
+        self.fill_placeholders(
+            self.pr_body.as_deref().unwrap_or(DEFAULT_PR_BODY),
+            repo,
+            ecosystems,
+        )
+    }
+
+    pub fn source_repo(&self) -> &str {
+        self.source_repo.as_deref().unwrap_or(DEFAULT_SOURCE_REPO)
+    }
+
+    pub fn helm_ecosystem(&self) -> &str {
+        self.helm_ecosystem
+            .as_deref()
+            .unwrap_or(DEFAULT_HELM_ECOSYSTEM)
+    }
+
+    /// The header of generated configs, followed by an empty line.
+    pub fn header(&self) -> String {
+        let header =
+            self.fill_source_placeholders(self.header.as_deref().unwrap_or(DEFAULT_HEADER));
+        format!("{}\n\n", header.trim_end())
+    }
+
+    /// Ensures that the header only consists of YAML comments.
+    pub fn validate_header(&self) -> anyhow::Result<()> {
+        let header = self.header();
+        let invalid = header
+            .lines()
+            .find(|line| !line.is_empty() && !line.starts_with('#'));
+        anyhow::ensure!(
+            invalid.is_none(),
+            "every line of the header must be a comment starting with #, got: {}",
+            invalid.unwrap_or_default()
+        );
+
+        Ok(())
+    }
+
+    fn fill_placeholders(&self, template: &str, repo: &str, ecosystems: &[String]) -> String {
+        self.fill_source_placeholders(template)
+            .replace("{repo}", repo)
+            .replace("{ecosystems}", &ecosystems.join(", "))
+    }
+
+    /// Fills the placeholders that are the same for every repo. Without a maintainer,
+    /// `{maintainer}` refers to the maintainers of the source repo.
+    pub fn fill_source_placeholders(&self, template: &str) -> String {
+        let source_repo = self.source_repo();
+        let source_repo_name = source_repo
+            .split_once('/')
+            .map_or(source_repo, |(_, name)| name);
+        let maintainer = match &self.maintainer {
+            Some(maintainer) => format!("@{maintainer}"),
+            None => format!("the maintainers of {source_repo}"),
+        };
+
+        template
+            .replace("{source_repo}", source_repo)
+            .replace("{source_repo_name}", source_repo_name)
+            .replace("{maintainer}", &maintainer)
+    }
+}
+
+/// A private registry that is added to every update of the given ecosystems.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RegistryTemplate {
+    /// Package ecosystems (e.g. "npm" or "docker") whose updates use this registry.
+    pub ecosystems: Vec<String>,
+    #[serde(flatten)]
+    pub registry: Registry,
+}
+
+/// Defaults applied to every generated update block of a repo, depending on its asset level.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub schedule: Schedule,
+    pub open_pull_requests_limit: Option<u32>,
+    pub open_pull_requests_limits: IndexMap<String, u32>,
+    pub commit_messages: IndexMap<String, CommitMessage>,
+    pub versioning_strategies: IndexMap<String, String>,
+    pub internal_packages: IndexMap<String, Vec<String>>,
+    pub allow: IndexMap<String, Vec<DependencyRule>>,
+    pub insecure_external_code_execution: Vec<String>,
+    pub groups: IndexMap<String, Group>,
+    pub cooldown: Cooldown,
+    pub no_cooldown_ecosystems: Vec<String>,
+    /// Omits the cooldown of every update, including those from overrides.
+    pub no_cooldown: bool,
+    /// Generates updates for ecosystems Dependabot only supports in beta.
+    pub enable_beta_ecosystems: bool,
+    /// Package ecosystems (e.g. "docker") that get no updates at all.
+    pub excluded_ecosystems: Vec<String>,
+    /// Package ecosystem of the updates for Helm charts.
+    pub helm_ecosystem: String,
+    pub registries: IndexMap<String, RegistryTemplate>,
+    pub reviewers: Option<Vec<String>>,
+    pub labels: Option<Vec<String>>,
+}
+
+impl Policy {
+    /// The policy of repos with the given asset level. Only the schedule and cooldown vary by
+    /// level, the groups are the same for every level.
+    pub fn for_level(level: AssetLevel, defaults: &PolicyDefaults) -> Policy {
+        let default = Policy::base(defaults);
+
+        match level {
+            // Production gets fixes quickly, so check daily and only wait a few days. A schedule
+            // set in the defaults is kept, the org chose it for every level.
+            AssetLevel::Production => Policy {
+                schedule: match defaults.schedule {
+                    Some(_) => default.schedule,
+                    None => Schedule {
+                        interval: "daily".to_string(),
+                        day: None,
+                        ..default.schedule
+                    },
+                },
+                cooldown: Cooldown {
+                    default_days: Some(3),
+                    ..default.cooldown
+                },
+                ..default
+            },
+            // Internal tooling can wait longer for updates to settle.
+            AssetLevel::Corporate => Policy {
+                cooldown: Cooldown {
+                    default_days: Some(14),
+                    ..default.cooldown
+                },
+                ..default
+            },
+            AssetLevel::Playground
+            | AssetLevel::ResearchNDevelopment
+            | AssetLevel::NonEssentialProduction => default,
+        }
+    }
+
+    fn base(defaults: &PolicyDefaults) -> Policy {
+        let group_exclude_patterns = non_empty(&defaults.group_exclude_patterns);
+
+        let schedule = defaults.schedule.clone().unwrap_or(Schedule {
+            interval: "weekly".to_string(),
+            day: Some("saturday".to_string()),
+            time: None, // Some("03:00".to_string()),
+            timezone: Some("America/Los_Angeles".to_string()),
+            ..Schedule::default()
+        });
+        let mut groups = IndexMap::from([
+            (
+                "security".to_string(),
+                Group {
+                    applies_to: Some("security-updates".to_string()),
+                    update_types: Some(vec!["minor".to_string(), "patch".to_string()]),
+                    exclude_patterns: group_exclude_patterns.clone(),
+                    ..Group::default()
+                },
+            ),
+            (
+                "patch".to_string(),
+                Group {
+                    applies_to: Some("version-updates".to_string()),
+                    update_types: Some(vec!["patch".to_string()]),
+                    exclude_patterns: group_exclude_patterns.clone(),
+                    ..Group::default()
+                },
+            ),
+            // No major groups, to avoid grouping of them.
+            (
+                "minor".to_string(),
+                Group {
+                    applies_to: Some("version-updates".to_string()),
+                    update_types: Some(vec!["minor".to_string(), "patch".to_string()]),
+                    exclude_patterns: group_exclude_patterns.clone(),
+                    ..Group::default()
+                },
+            ),
+        ]);
+        groups.extend(defaults.extra_groups.clone());
+
+        // Dependabot puts an update into the first matching group, so this has to come before the
+        // patch and minor groups. Production dependencies keep using those.
+        if defaults.group_development_dependencies {
+            groups.shift_insert(
+                1,
+                "development".to_string(),
+                Group {
+                    applies_to: Some("version-updates".to_string()),
+                    dependency_type: Some("development".to_string()),
+                    update_types: Some(vec!["minor".to_string(), "patch".to_string()]),
+                    exclude_patterns: group_exclude_patterns.clone(),
+                    ..Group::default()
+                },
+            );
+        }
+
+        let mut cooldown = Cooldown {
+            default_days: Some(7),
+            exclude: non_empty(&defaults.cooldown_exclude),
+            ..Cooldown::default()
+        };
+        if let Some(default_cooldown) = &defaults.cooldown {
+            cooldown = cooldown.override_config(default_cooldown);
+        }
+
+        Policy {
+            schedule,
+            open_pull_requests_limit: Some(5),
+            open_pull_requests_limits: defaults.open_pull_requests_limits.clone(),
+            commit_messages: defaults.commit_messages.clone(),
+            versioning_strategies: defaults.versioning_strategies.clone(),
+            internal_packages: defaults.internal_packages.clone(),
+            allow: defaults.allow.clone(),
+            insecure_external_code_execution: defaults.insecure_external_code_execution.clone(),
+            groups,
+            cooldown,
+            no_cooldown_ecosystems: defaults.no_cooldown_ecosystems.clone(),
+            no_cooldown: false,
+            enable_beta_ecosystems: false,
+            excluded_ecosystems: vec![],
+            helm_ecosystem: defaults.helm_ecosystem().to_string(),
+            registries: defaults.registries.clone(),
+            reviewers: non_empty(&defaults.reviewers),
+            labels: non_empty(&defaults.labels),
+        }
+    }
+
+    /// The ecosystem specific limit, falling back to the global one.
+    pub fn open_pull_requests_limit(&self, ecosystem: &str) -> Option<u32> {
+        self.open_pull_requests_limits
+            .get(ecosystem)
+            .copied()
+            .or(self.open_pull_requests_limit)
+    }
+
+    /// The cooldown of `ecosystem`, unless it is disabled for it. Git submodules never get one.
+    pub fn cooldown(&self, ecosystem: &str) -> Option<Cooldown> {
+        let disabled = ecosystem == "gitsubmodule"
+            || self
+                .no_cooldown_ecosystems
+                .iter()
+                .any(|no_cooldown| no_cooldown == ecosystem);
+
+        (!disabled).then(|| self.cooldown.clone())
+    }
+
+    pub fn commit_message(&self, ecosystem: &str) -> Option<CommitMessage> {
+        self.commit_messages.get(ecosystem).cloned()
+    }
+
+    pub fn versioning_strategy(&self, ecosystem: &str) -> Option<String> {
+        self.versioning_strategies.get(ecosystem).cloned()
+    }
+
+    pub fn is_excluded(&self, ecosystem: &str) -> bool {
+        self.excluded_ecosystems
+            .iter()
+            .any(|excluded| excluded == ecosystem)
+    }
+
+    pub fn allow(&self, ecosystem: &str) -> Option<Vec<DependencyRule>> {
+        self.allow
+            .get(ecosystem)
+            .filter(|rules| !rules.is_empty())
+            .cloned()
+    }
+
+    pub fn insecure_external_code_execution(&self, ecosystem: &str) -> Option<String> {
+        self.insecure_external_code_execution
+            .iter()
+            .any(|allowed| allowed == ecosystem)
+            .then(|| "allow".to_string())
+    }
+
+    /// Ignore rules for the internal packages of `ecosystem`.
+    pub fn ignore(&self, ecosystem: &str) -> Option<Vec<DependencyRule>> {
+        let rules = self
+            .internal_packages
+            .get(ecosystem)?
+            .iter()
+            .map(|pattern| DependencyRule {
+                dependency_name: Some(pattern.clone()),
+                dependency_type: None,
+                versions: None,
+                update_types: None,
+            })
+            .collect::<Vec<_>>();
+
+        if rules.is_empty() { None } else { Some(rules) }
+    }
+
+    /// Names of the generated registries that updates of `ecosystem` should use.
+    pub fn registry_names(&self, ecosystem: &str) -> Option<Vec<String>> {
+        let names = self
+            .registries
+            .iter()
+            .filter(|(_, template)| template.ecosystems.iter().any(|e| e == ecosystem))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        non_empty(&names)
+    }
+}
+
+/// Empty lists are omitted from the generated config.
+fn non_empty(list: &[String]) -> Option<Vec<String>> {
+    if list.is_empty() {
+        None
+    } else {
+        Some(list.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn production_checks_daily_by_default() {
+        let schedule =
+            Policy::for_level(AssetLevel::Production, &PolicyDefaults::default()).schedule;
+
+        schedule.validate().unwrap();
+        assert_eq!(schedule.interval, "daily");
+        assert_eq!(schedule.day, None);
+    }
+
+    #[test]
+    fn production_keeps_default_schedule() {
+        let schedule = Schedule {
+            interval: "cron".to_string(),
+            cronjob: Some("0 3 * * 1".to_string()),
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Schedule::default()
+        };
+        let defaults = PolicyDefaults {
+            schedule: Some(schedule.clone()),
+            ..PolicyDefaults::default()
+        };
+
+        for level in [
+            AssetLevel::Production,
+            AssetLevel::Corporate,
+            AssetLevel::Playground,
+        ] {
+            let policy = Policy::for_level(level, &defaults);
+            policy.schedule.validate().unwrap();
+            assert_eq!(policy.schedule, schedule);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Mutex;
+
+/// What to do with the PR of a repo, see --interactive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Proceed,
+    Skip,
+    Abort,
+}
+
+/// Asks on stdin before each PR is created or updated. Concurrently processed repos wait for
+/// their turn, so that only one question is asked at a time.
+pub struct Prompt {
+    enabled: bool,
+    turn: Mutex<()>,
+    aborted: AtomicBool,
+}
+
+impl Prompt {
+    pub fn new(enabled: bool) -> Prompt {
+        Prompt {
+            enabled,
+            turn: Mutex::new(()),
+            aborted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Shows the change of the config of `repo` and asks whether to apply it. Without
+    /// --interactive every change is applied, after an abort none is.
+    pub async fn confirm(
+        &self,
+        repo: &str,
+        existing: Option<&str>,
+        content: &str,
+    ) -> anyhow::Result<Decision> {
+        if !self.enabled {
+            return Ok(Decision::Proceed);
+        }
+
+        let _turn = self.turn.lock().await;
+        if self.aborted() {
+            return Ok(Decision::Abort);
+        }
+
+        let question = format!(
+            "{}\n{} dependabot config of {}? [y]es, [n]o, [a]bort: ",
+            diff(existing.unwrap_or_default(), content),
+            if existing.is_some() {
+                "Update"
+            } else {
+                "Create"
+            },
+            repo
+        );
+        // Reading stdin blocks, which must not stall the other repos.
+        let decision = tokio::task::spawn_blocking(move || ask(&question)).await??;
+        if decision == Decision::Abort {
+            self.aborted.store(true, Ordering::SeqCst);
+        }
+
+        Ok(decision)
+    }
+}
+
+fn ask(question: &str) -> anyhow::Result<Decision> {
+    let stdin = std::io::stdin();
+    loop {
+        print!("{question}");
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        // Treat a closed stdin like an abort instead of asking forever.
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            return Ok(Decision::Abort);
+        }
+
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Decision::Proceed),
+            "n" | "no" => return Ok(Decision::Skip),
+            "a" | "abort" => return Ok(Decision::Abort),
+            _ => continue,
+        }
+    }
+}
+
+/// A line based diff from `old` to `new`, good enough for configs of a few hundred lines.
+fn diff(old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of the remaining lines.
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        } else {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        }
+    }
+
+    lines.join("\n")
+}
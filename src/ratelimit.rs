@@ -0,0 +1,97 @@
+use anyhow::Context;
+use chrono::DateTime;
+use octocrab::Octocrab;
+use octocrab::models::{Rate, RateLimit};
+
+/// Below this share of the search quota a run that detects ecosystems via code search is likely
+/// throttled.
+const LOW_SEARCH_QUOTA: f64 = 0.1;
+
+/// Logs the remaining quota of the core, search and GraphQL rate limits. Querying them does not
+/// count against the limits. Failures are only logged, the run does not depend on this.
+pub async fn log_rate_limits(octocrab: &Octocrab, searching: bool) -> Option<RateLimit> {
+    let limits = match fetch(octocrab).await {
+        Ok(limits) => limits,
+        Err(e) => {
+            log::warn!("{:#}", e);
+            return None;
+        }
+    };
+
+    for (bucket, rate) in buckets(&limits) {
+        log::info!(
+            "Rate limit {}: {}/{} remaining, resets at {}",
+            bucket,
+            rate.remaining,
+            rate.limit,
+            reset_time(rate)
+        );
+    }
+
+    let search = &limits.resources.search;
+    if searching && (search.remaining as f64) < search.limit as f64 * LOW_SEARCH_QUOTA {
+        log::warn!(
+            "Only {} search requests remain until {}, finding ecosystems may be throttled. Pass --ecosystems-cache to reuse earlier results.",
+            search.remaining,
+            reset_time(search)
+        );
+    }
+
+    Some(limits)
+}
+
+/// Logs how much of each rate limit the run consumed since `start`.
+pub async fn log_consumed(octocrab: &Octocrab, start: &RateLimit) {
+    let limits = match fetch(octocrab).await {
+        Ok(limits) => limits,
+        Err(e) => {
+            log::warn!("{:#}", e);
+            return;
+        }
+    };
+
+    for ((bucket, before), (_, after)) in buckets(start).into_iter().zip(buckets(&limits)) {
+        // A reset during the run hides what was used before it.
+        if before.reset == after.reset {
+            log::info!(
+                "Rate limit {}: used {} during the run, {}/{} remaining",
+                bucket,
+                after.used.saturating_sub(before.used),
+                after.remaining,
+                after.limit
+            );
+        } else {
+            log::info!(
+                "Rate limit {}: used at least {} during the run, {}/{} remaining",
+                bucket,
+                after.used,
+                after.remaining,
+                after.limit
+            );
+        }
+    }
+}
+
+async fn fetch(octocrab: &Octocrab) -> anyhow::Result<RateLimit> {
+    octocrab
+        .ratelimit()
+        .get()
+        .await
+        .context("failed to fetch rate limits")
+}
+
+fn buckets(limits: &RateLimit) -> Vec<(&'static str, &Rate)> {
+    let mut buckets = vec![
+        ("core", &limits.resources.core),
+        ("search", &limits.resources.search),
+    ];
+    if let Some(graphql) = &limits.resources.graphql {
+        buckets.push(("graphql", graphql));
+    }
+    buckets
+}
+
+fn reset_time(rate: &Rate) -> String {
+    DateTime::from_timestamp(rate.reset as i64, 0)
+        .map_or_else(|| rate.reset.to_string(), |reset| reset.to_rfc3339())
+}
@@ -0,0 +1,370 @@
+use crate::github::AssetLevel;
+use anyhow::Context;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// What happened to the dependabot config of a single repo.
+#[derive(Debug, Clone, Copy, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoStatus {
+    /// The repo was not processed, see the reason of the record.
+    Skipped,
+    /// No ecosystems were detected, so no config was generated.
+    NoConfig,
+    /// The generated config matches the one on the PR branch.
+    Unchanged,
+    /// The config was committed to a freshly created PR branch.
+    Created,
+    /// The config was committed to an existing PR branch.
+    Updated,
+    /// The config would be committed to a fresh PR branch, but --create-pr was not passed.
+    WouldCreate,
+    /// The config would replace an existing one, but --create-pr was not passed.
+    WouldUpdate,
+    /// The orphaned config would be deleted, but --create-pr was not passed.
+    WouldRemove,
+    /// The config was only printed because of --print-only.
+    Printed,
+    /// The committed config differs from the generated one, see --check.
+    Drifted,
+    /// The orphaned config was deleted on the PR branch because of --remove-orphaned.
+    Removed,
+    /// The config was written to the local directory given by --output-dir.
+    Written,
+}
+
+impl RepoStatus {
+    /// All statuses in the order they are summarized.
+    const ALL: [RepoStatus; 12] = [
+        RepoStatus::Created,
+        RepoStatus::Updated,
+        RepoStatus::Removed,
+        RepoStatus::WouldCreate,
+        RepoStatus::WouldUpdate,
+        RepoStatus::WouldRemove,
+        RepoStatus::Unchanged,
+        RepoStatus::Drifted,
+        RepoStatus::Printed,
+        RepoStatus::Written,
+        RepoStatus::NoConfig,
+        RepoStatus::Skipped,
+    ];
+
+    /// Whether a PR was (or in a dry run would be) created or updated.
+    pub fn is_change(&self) -> bool {
+        matches!(
+            self,
+            RepoStatus::Created
+                | RepoStatus::Updated
+                | RepoStatus::Removed
+                | RepoStatus::WouldCreate
+                | RepoStatus::WouldUpdate
+                | RepoStatus::WouldRemove
+        )
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RepoStatus::Skipped => "skipped",
+            RepoStatus::NoConfig => "without config",
+            RepoStatus::Unchanged => "unchanged",
+            RepoStatus::Created => "created",
+            RepoStatus::Updated => "updated",
+            RepoStatus::WouldCreate => "would create",
+            RepoStatus::WouldUpdate => "would update",
+            RepoStatus::WouldRemove => "would remove",
+            RepoStatus::Printed => "printed",
+            RepoStatus::Drifted => "drifted",
+            RepoStatus::Removed => "removed",
+            RepoStatus::Written => "written",
+        }
+    }
+}
+
+/// A single NDJSON record describing the outcome for a repo.
+#[derive(Debug, Serialize)]
+pub struct RepoReport<'a> {
+    pub repo: &'a str,
+    pub level: Option<AssetLevel>,
+    pub status: RepoStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'a str>,
+    pub ecosystems: Vec<String>,
+    /// Distinct schedule slots of the generated update blocks, see [`crate::dependabot::Schedule::slot`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<String>,
+}
+
+impl<'a> RepoReport<'a> {
+    /// The record of a repo that was not processed for the given reason.
+    pub fn skipped(repo: &'a str, level: Option<AssetLevel>, reason: &'a str) -> RepoReport<'a> {
+        RepoReport {
+            repo,
+            level,
+            status: RepoStatus::Skipped,
+            reason: Some(reason),
+            ecosystems: vec![],
+            schedules: vec![],
+        }
+    }
+}
+
+/// The NDJSON record of a repo that failed to process, e.g. because of an API error.
+#[derive(Debug, Serialize)]
+struct FailureReport<'a> {
+    repo: &'a str,
+    status: &'static str,
+    error: String,
+}
+
+/// Counts how many repos run their updates in each schedule slot. Without staggering, all repos
+/// in one slot open their PRs at the same time and overload CI.
+#[derive(Debug, Default)]
+pub struct ScheduleSlots {
+    repos_per_slot: IndexMap<String, usize>,
+}
+
+impl ScheduleSlots {
+    pub fn add(&mut self, report: &RepoReport) {
+        for slot in &report.schedules {
+            *self.repos_per_slot.entry(slot.clone()).or_default() += 1;
+        }
+    }
+
+    /// Logs the number of repos per slot and warns about slots with more than `threshold` repos.
+    pub fn warn_overloaded(&self, threshold: usize) {
+        for (slot, repos) in &self.repos_per_slot {
+            log::info!("{} repos are scheduled at {}", repos, slot);
+        }
+        for warning in self.overload_warnings(threshold) {
+            log::warn!("{warning}");
+        }
+    }
+
+    /// The warnings about slots with more than `threshold` repos.
+    fn overload_warnings(&self, threshold: usize) -> Vec<String> {
+        self.repos_per_slot
+            .iter()
+            .filter(|(_, repos)| **repos > threshold)
+            .map(|(slot, repos)| {
+                format!(
+                    "{repos} repos share the schedule slot {slot}, which is more than {threshold}. Stagger them by giving some of these repos a different schedule time via an override in the --dependabot-overrides file, e.g. schedule = {{ interval = \"weekly\", time = \"05:00\" }}."
+                )
+            })
+            .collect()
+    }
+}
+
+/// Counts the outcomes of all repos, so that big runs can be judged at a glance.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    statuses: IndexMap<RepoStatus, usize>,
+    skip_reasons: IndexMap<String, usize>,
+    failed: usize,
+}
+
+impl RunSummary {
+    pub fn add(&mut self, report: &RepoReport) {
+        *self.statuses.entry(report.status).or_default() += 1;
+        if report.status == RepoStatus::Skipped {
+            let reason = report.reason.unwrap_or("unknown").to_string();
+            *self.skip_reasons.entry(reason).or_default() += 1;
+        }
+    }
+
+    pub fn add_failure(&mut self) {
+        self.failed += 1;
+    }
+
+    /// Prints the counts to stderr, which keeps stdout free for the NDJSON report and configs.
+    pub fn print(&self) {
+        let mut counts = RepoStatus::ALL
+            .iter()
+            .filter_map(|status| {
+                let count = self.statuses.get(status)?;
+                let mut line = format!("{} {}", count, status.label());
+                if *status == RepoStatus::Skipped {
+                    let reasons = self
+                        .skip_reasons
+                        .iter()
+                        .map(|(reason, count)| format!("{reason}: {count}"))
+                        .collect::<Vec<_>>();
+                    line.push_str(&format!(" ({})", reasons.join(", ")));
+                }
+                Some(line)
+            })
+            .collect::<Vec<_>>();
+        if self.failed > 0 {
+            counts.push(format!("{} failed", self.failed));
+        }
+
+        if counts.is_empty() {
+            eprintln!("Summary: no repos processed");
+        } else {
+            eprintln!("Summary: {}", counts.join(", "));
+        }
+    }
+}
+
+/// Streams one JSON record per line as soon as a repo is processed, so that the report survives
+/// a crash in the middle of a run.
+pub struct NdjsonReport {
+    writer: Option<Box<dyn Write>>,
+}
+
+impl NdjsonReport {
+    /// Opens the report at `path`, `-` writes to stdout. Without a path records are discarded.
+    pub fn open(path: Option<&str>) -> anyhow::Result<NdjsonReport> {
+        let writer: Box<dyn Write> = match path {
+            None => return Ok(NdjsonReport { writer: None }),
+            Some("-") => Box::new(std::io::stdout()),
+            Some(path) => Box::new(BufWriter::new(
+                File::create(path).context("failed to create NDJSON report file")?,
+            )),
+        };
+
+        Ok(NdjsonReport::with_writer(writer))
+    }
+
+    /// Streams the records to `writer`.
+    pub fn with_writer(writer: Box<dyn Write>) -> NdjsonReport {
+        NdjsonReport {
+            writer: Some(writer),
+        }
+    }
+
+    pub fn write(&mut self, record: &RepoReport) -> anyhow::Result<()> {
+        self.write_record(record)
+    }
+
+    /// Writes a record with the `failed` status and the error chain of the repo.
+    pub fn write_failure(&mut self, repo: &str, error: &anyhow::Error) -> anyhow::Result<()> {
+        self.write_record(&FailureReport {
+            repo,
+            status: "failed",
+            error: format!("{error:#}"),
+        })
+    }
+
+    fn write_record(&mut self, record: &impl Serialize) -> anyhow::Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+
+        serde_json::to_writer(&mut *writer, record).context("failed to write NDJSON record")?;
+        writer.write_all(b"\n")?;
+        writer.flush().context("failed to flush NDJSON report")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// An in-memory writer whose contents stay readable after it was handed to the report.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn report(schedules: &[&str]) -> RepoReport<'static> {
+        RepoReport {
+            repo: "engine",
+            level: Some(AssetLevel::Production),
+            status: RepoStatus::Created,
+            reason: None,
+            ecosystems: vec![],
+            schedules: schedules.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn schedule_slots_count_repos() {
+        let mut slots = ScheduleSlots::default();
+        slots.add(&report(&["daily America/Los_Angeles"]));
+        slots.add(&report(&[
+            "daily America/Los_Angeles",
+            "weekly saturday America/Los_Angeles",
+        ]));
+        slots.add(&report(&[]));
+
+        assert_eq!(
+            slots.repos_per_slot,
+            IndexMap::from([
+                ("daily America/Los_Angeles".to_string(), 2),
+                ("weekly saturday America/Los_Angeles".to_string(), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn warns_about_slots_above_threshold() {
+        let mut slots = ScheduleSlots::default();
+        for _ in 0..3 {
+            slots.add(&report(&["daily America/Los_Angeles"]));
+        }
+        slots.add(&report(&["weekly saturday America/Los_Angeles"]));
+
+        assert!(slots.overload_warnings(3).is_empty());
+
+        let warnings = slots.overload_warnings(2);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(
+            "3 repos share the schedule slot daily America/Los_Angeles, which is more than 2."
+        ));
+        assert!(warnings[0].contains("--dependabot-overrides"));
+    }
+
+    #[test]
+    fn writes_one_line_per_repo() {
+        let buffer = SharedBuffer::default();
+        let mut ndjson = NdjsonReport::with_writer(Box::new(buffer.clone()));
+
+        ndjson.write(&report(&[])).unwrap();
+        ndjson
+            .write(&RepoReport {
+                repo: "website",
+                status: RepoStatus::Unchanged,
+                ..report(&[])
+            })
+            .unwrap();
+        ndjson
+            .write_failure("docs", &anyhow::anyhow!("rate limited"))
+            .unwrap();
+
+        let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert!(output.ends_with('\n'));
+        let records = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .map(|record| {
+                (
+                    record["repo"].as_str().unwrap().to_string(),
+                    record["status"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            [
+                ("engine".to_string(), "created".to_string()),
+                ("website".to_string(), "unchanged".to_string()),
+                ("docs".to_string(), "failed".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::dependabot::DependabotConfig;
+use anyhow::Context;
+
+/// JSON schema for dependabot.yml, see `schema/README.md` for its source.
+const DEPENDABOT_SCHEMA: &str = include_str!("../schema/dependabot-2.0.json");
+
+/// Validates a generated config against the bundled Dependabot JSON schema.
+///
+/// This catches structural errors like unknown keys or invalid enum values before a PR is opened.
+pub fn validate_schema(config: &DependabotConfig) -> anyhow::Result<()> {
+    let instance = serde_json::to_value(config).context("failed to convert config to JSON")?;
+    validate_instance(&instance)
+}
+
+/// Validates any config, e.g. a hand-written one, against the bundled Dependabot JSON schema.
+pub fn validate_instance(instance: &serde_json::Value) -> anyhow::Result<()> {
+    let schema: serde_json::Value =
+        serde_json::from_str(DEPENDABOT_SCHEMA).context("failed to parse bundled schema")?;
+    let validator = jsonschema::validator_for(&schema).context("failed to compile schema")?;
+
+    let errors = validator
+        .iter_errors(instance)
+        .map(|error| format!("{} at {}", error, error.instance_path()))
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "config does not match the dependabot schema:\n{}",
+            errors.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependabot::{Schedule, Update};
+
+    fn config(interval: &str) -> DependabotConfig {
+        DependabotConfig {
+            version: 2,
+            updates: vec![Update {
+                package_ecosystem: "cargo".to_string(),
+                directory: Some("/".to_string()),
+                schedule: Schedule {
+                    interval: interval.to_string(),
+                    ..Schedule::default()
+                },
+                ..Update::default()
+            }],
+            ..DependabotConfig::default()
+        }
+    }
+
+    #[test]
+    fn valid_config_matches_schema() {
+        validate_schema(&config("weekly")).unwrap();
+    }
+
+    #[test]
+    fn invalid_config_fails_schema() {
+        let error = validate_schema(&config("hourly")).unwrap_err();
+        assert!(error.to_string().contains("/updates/0/schedule/interval"));
+
+        let unknown_key = serde_json::json!({
+            "version": 2,
+            "updates": [{
+                "package-ecosystem": "cargo",
+                "directory": "/",
+                "schedule": { "interval": "weekly" },
+                "frequency": "often",
+            }],
+        });
+        assert!(validate_instance(&unknown_key).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::cache;
+use anyhow::Context;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+
+/// Remembers the config generated for each repo, so that daily runs can skip repos whose default
+/// branch already has the config the same inputs would generate again.
+pub struct StateFile {
+    path: Option<String>,
+    repos: Mutex<IndexMap<String, RepoState>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RepoState {
+    /// Hash of the generated config file.
+    config_hash: String,
+    /// Hash of everything the config was generated from, e.g. overrides, asset level and detected
+    /// ecosystems.
+    inputs_hash: String,
+}
+
+impl StateFile {
+    /// Loads the state from `path`, a missing file is an empty state. Without a path nothing is
+    /// remembered.
+    pub fn open(path: Option<&str>) -> anyhow::Result<StateFile> {
+        let repos = match path {
+            Some(path) if fs::exists(path)? => {
+                let content = fs::read_to_string(path).context("failed to read state file")?;
+                serde_json::from_str(&content).context("failed to parse state file")?
+            }
+            _ => IndexMap::new(),
+        };
+
+        Ok(StateFile {
+            path: path.map(str::to_string),
+            repos: Mutex::new(repos),
+        })
+    }
+
+    /// Whether an earlier run generated a config for `repo`, which is a candidate for skipping.
+    pub fn contains(&self, repo: &str) -> bool {
+        self.repos.lock().expect("lock poisoned").contains_key(repo)
+    }
+
+    /// Whether the config on the default branch is the one generated last time from the same
+    /// inputs.
+    pub fn is_up_to_date(&self, repo: &str, config_on_main: &str, inputs: &str) -> bool {
+        let expected = RepoState {
+            config_hash: hash(config_on_main),
+            inputs_hash: hash(inputs),
+        };
+
+        self.repos.lock().expect("lock poisoned").get(repo) == Some(&expected)
+    }
+
+    pub fn record(&self, repo: &str, config: &str, inputs: &str) {
+        self.repos.lock().expect("lock poisoned").insert(
+            repo.to_string(),
+            RepoState {
+                config_hash: hash(config),
+                inputs_hash: hash(inputs),
+            },
+        );
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let repos = self.repos.lock().expect("lock poisoned");
+        cache::write_atomically(path, &serde_json::to_string_pretty(&*repos)?)
+            .context("failed to write state file")?;
+
+        Ok(())
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+fn hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_records_and_saves_keep_every_repo() {
+        let dir = std::env::temp_dir().join(format!(
+            "dependabot-org-config-{}-state",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json").to_string_lossy().to_string();
+        let _ = fs::remove_file(&path);
+
+        let state = StateFile::open(Some(&path)).unwrap();
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let state = &state;
+                scope.spawn(move || {
+                    for i in 0..20 {
+                        let repo = format!("repo-{thread}-{i}");
+                        state.record(&repo, &format!("config of {repo}"), "inputs");
+                        state.save().unwrap();
+                    }
+                });
+            }
+        });
+        state.save().unwrap();
+
+        let reopened = StateFile::open(Some(&path)).unwrap();
+        for thread in 0..8 {
+            for i in 0..20 {
+                let repo = format!("repo-{thread}-{i}");
+                assert!(reopened.is_up_to_date(&repo, &format!("config of {repo}"), "inputs"));
+            }
+        }
+        // No temporary file of the atomic writes is left behind.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+}
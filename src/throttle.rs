@@ -0,0 +1,122 @@
+use crate::prompt::Prompt;
+use crate::report::RepoStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard, Semaphore};
+use tokio::time::sleep;
+
+/// Everything that limits writes to GitHub, shared by all concurrently processed repos.
+pub struct Writes {
+    /// Limits concurrent writes, see --write-concurrency.
+    pub permits: Semaphore,
+    pub pr_throttle: PrThrottle,
+    pub limit: WriteLimit,
+    /// Caps the PRs opened in repos without an open bot PR, see --max-open-prs.
+    pub new_prs: WriteLimit,
+    /// Confirms each PR, see --interactive.
+    pub prompt: Prompt,
+}
+
+/// Pauses PR creation after every batch of PRs, as bursts of PRs trip GitHub's abuse detection.
+/// Only PRs that were actually opened count towards a batch.
+pub struct PrThrottle {
+    batch_size: Option<usize>,
+    pause: Duration,
+    batches: Mutex<Batches>,
+}
+
+#[derive(Default)]
+struct Batches {
+    created: usize,
+    /// The number of created PRs at the last pause, so that repos without a new PR in between
+    /// don't pause again.
+    paused_at: usize,
+}
+
+impl PrThrottle {
+    pub fn new(batch_size: Option<usize>, pause: Duration) -> PrThrottle {
+        PrThrottle {
+            batch_size,
+            pause,
+            batches: Mutex::default(),
+        }
+    }
+
+    /// Waits until the next PR may be opened. Concurrent callers are blocked during the pause and
+    /// until the returned slot is dropped, so that a batch cannot be overshot.
+    pub async fn wait(&self) -> PrSlot<'_> {
+        let Some(batch_size) = self.batch_size else {
+            return PrSlot { batches: None };
+        };
+
+        let mut batches = self.batches.lock().await;
+        if batches.created > batches.paused_at && batches.created % batch_size == 0 {
+            log::info!(
+                "Created {} PRs, pausing for {}s",
+                batches.created,
+                self.pause.as_secs()
+            );
+            sleep(self.pause).await;
+            batches.paused_at = batches.created;
+        }
+
+        PrSlot {
+            batches: Some(batches),
+        }
+    }
+}
+
+/// The turn of a repo to open a PR, see [`PrThrottle::wait`].
+pub struct PrSlot<'a> {
+    batches: Option<MutexGuard<'a, Batches>>,
+}
+
+impl PrSlot<'_> {
+    /// Counts the PR towards the current batch, once it was opened.
+    pub fn opened(mut self) {
+        if let Some(batches) = &mut self.batches {
+            batches.created += 1;
+        }
+    }
+}
+
+/// Caps how many repos get their PR created or updated in one run, to roll out in waves.
+pub struct WriteLimit {
+    limit: Option<usize>,
+    reserved: AtomicUsize,
+}
+
+impl WriteLimit {
+    pub fn new(limit: Option<usize>) -> WriteLimit {
+        WriteLimit {
+            limit,
+            reserved: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reserves one write before it happens, so that concurrent repos cannot exceed the limit.
+    /// Returns false once the limit is reached.
+    pub fn try_reserve(&self) -> bool {
+        let Some(limit) = self.limit else {
+            return true;
+        };
+
+        self.reserved
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved| {
+                (reserved < limit).then_some(reserved + 1)
+            })
+            .is_ok()
+    }
+
+    /// Gives the reservation back if the repo turned out to need no changes.
+    pub fn finish(&self, status: RepoStatus) {
+        if self.limit.is_some() && !status.is_change() {
+            self.reserved.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn reached(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.reserved.load(Ordering::SeqCst) >= limit)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::forge::{Forge, Repo};
+use crate::{Ecosystem, add_manifest, default_branch, is_dockerfile};
+use indexmap::IndexMap;
+
+/// Detects the ecosystems of the selected repos by walking their git tree on the default branch.
+/// Unlike code search this sees every file and does not use the search quota, but costs one
+/// request per repo. Repos whose tree can't be fetched are skipped with a warning.
+pub async fn find_ecosystems_in_trees(
+    forge: &impl Forge,
+    repos: &[&Repo],
+) -> IndexMap<String, Vec<(String, Ecosystem)>> {
+    let mut ecosystems = IndexMap::new();
+
+    for repo in repos {
+        if repo.size == Some(0) {
+            continue;
+        }
+
+        let branch = default_branch(repo);
+        log::info!("Walking the tree of {} on {}", repo.name, branch);
+
+        let tree = match forge.list_files(&repo.name, branch).await {
+            Ok(tree) => tree,
+            Err(e) => {
+                log::warn!(
+                    "Skipping the ecosystems of {} as its tree could not be fetched: {:#}",
+                    repo.name,
+                    e
+                );
+                continue;
+            }
+        };
+
+        if tree.truncated {
+            log::warn!(
+                "The tree of {} is truncated, some manifests may be missing",
+                repo.name
+            );
+        }
+
+        let paths = tree.paths.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let mut entries = vec![];
+        for (path, ecosystem) in classify_paths(&paths) {
+            // Same format as the paths of code search results, see `manifest_directory`.
+            add_manifest(
+                &mut entries,
+                (
+                    format!("/repositories/{}/contents/{}", repo.id, path),
+                    ecosystem,
+                ),
+            );
+        }
+
+        if !entries.is_empty() {
+            ecosystems.insert(repo.full_name.clone(), entries);
+        }
+    }
+
+    ecosystems
+}
+
+/// Maps the file paths of a repo to ecosystems, using the same file names as code search.
+fn classify_paths<'a>(paths: &[&'a str]) -> Vec<(&'a str, Ecosystem)> {
+    let directory = |path: &str| path.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+    let file_name = |path: &'a str| path.rsplit_once('/').map_or(path, |(_, name)| name);
+
+    let cargo_directories = paths
+        .iter()
+        .filter(|path| file_name(path) == "Cargo.toml")
+        .map(|path| directory(path))
+        .collect::<Vec<_>>();
+    let uv_directories = paths
+        .iter()
+        .filter(|path| file_name(path) == "uv.lock")
+        .map(|path| directory(path))
+        .collect::<Vec<_>>();
+
+    paths
+        .iter()
+        .filter(|path| !path.split('/').any(|segment| segment == "node_modules"))
+        .filter_map(|path| {
+            let ecosystem = match file_name(path) {
+                // Code search only finds workspace roots by their content. Without contents, the
+                // outermost Cargo.toml is the best guess for the root.
+                "Cargo.toml" => {
+                    let dir = directory(path);
+                    let nested = cargo_directories.iter().any(|other| {
+                        *other != dir && (other.is_empty() || dir.starts_with(&format!("{other}/")))
+                    });
+                    (!nested).then_some(Ecosystem::Cargo)
+                }
+                "package.json" => Some(Ecosystem::Npm),
+                "go.mod" => Some(Ecosystem::Go),
+                ".gitmodules" => Some(Ecosystem::Submodule),
+                ".terraform.lock.hcl" => Some(Ecosystem::Terraform),
+                "requirements.txt" | "pyproject.toml" | "poetry.lock" | "Pipfile"
+                | "Pipfile.lock" => {
+                    // uv takes precedence within a directory, as for code search.
+                    (!uv_directories.contains(&directory(path))).then_some(Ecosystem::Pip)
+                }
+                "uv.lock" => Some(Ecosystem::Uv),
+                "Gemfile.lock" => Some(Ecosystem::Bundler),
+                "pubspec.yaml" => Some(Ecosystem::Pub),
+                "Chart.yaml" => Some(Ecosystem::Helm),
+                name if is_dockerfile(name) => Some(Ecosystem::Docker),
+                _ => None,
+            }?;
+
+            Some((*path, ecosystem))
+        })
+        .collect()
+}